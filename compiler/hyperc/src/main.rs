@@ -4,30 +4,174 @@
  * SPDX-License-Identifier: MIT
  */
 
-use hyperc_diagnostics::Diagnostic;
-use hyperc_lexer::Lexer;
+mod options;
 
-use color_eyre::Result;
+use crate::options::{ErrorFormat, Options};
+
+use hyperc_diagnostics::{
+    fixit::{self, Applicability},
+    ice, registry, sarif,
+    sink::{DisplayStyle, JsonSink, MemorySink, TerminalSink},
+    Diagnostic,
+};
+use hyperc_preprocessor::Preprocessor;
+use hyperc_span::FileId;
+
+use color_eyre::{eyre::bail, Result};
+use std::{env, fs, process};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let test_program = unindent::unindent(
-        r#"
-        int main() {
-            return 0;
-        }
-        "#,
-    );
+    let options = Options::parse(env::args().skip(1))?;
 
-    let diagnostic = Diagnostic::new("test.c", &test_program);
+    if let Some(code) = &options.explain {
+        let Some(explanation) = registry::explanation(code) else {
+            bail!("`{}` is not a valid error code", code);
+        };
 
-    let mut lexer = Lexer::new(&diagnostic, &test_program);
-    let Some(tokens) = lexer.lex() else {
+        print!("{}", explanation);
         return Ok(());
+    }
+
+    let (file, source) = match &options.input {
+        Some(input) => (input.display().to_string(), fs::read(input)?),
+        None => {
+            let test_program = unindent::unindent(
+                r#"
+                int main() {
+                    return 0;
+                }
+                "#,
+            );
+
+            (String::from("test.c"), test_program.into_bytes())
+        }
+    };
+
+    let terminal_sink = |display_style| {
+        let sink = TerminalSink::new(options.color).with_display_style(display_style);
+        match options.message_length {
+            Some(message_length) => sink.with_message_length(message_length),
+            None => sink,
+        }
+    };
+
+    let sarif_sink = MemorySink::new();
+    let diagnostic = match options.error_format {
+        ErrorFormat::Human => Diagnostic::new().with_sink(terminal_sink(DisplayStyle::Rich)),
+        ErrorFormat::Short => Diagnostic::new().with_sink(terminal_sink(DisplayStyle::Short)),
+        ErrorFormat::Json => Diagnostic::new().with_sink(JsonSink),
+        ErrorFormat::Sarif => Diagnostic::new().with_sink(sarif_sink.clone()),
+    }
+    .with_buffering(true)
+    .with_warning_settings(options.warning_settings.clone())
+    .with_warnings_as_errors(options.warnings_as_errors)
+    .with_fatal_errors(options.fatal_errors)
+    .with_max_errors(options.max_errors);
+
+    let file_id = diagnostic.add_file(&file, String::from_utf8_lossy(&source));
+
+    let user_files = match ice::catch_ice(|| compile(&diagnostic, &options, file_id, &source)) {
+        Ok(user_files) => user_files,
+        Err(ice) => {
+            diagnostic.report_ice(ice.to_report(env!("CARGO_PKG_VERSION")));
+            Vec::new()
+        }
     };
+    diagnostic.flush();
+
+    if options.fixit && options.input.is_some() {
+        apply_fixits(&diagnostic, &user_files)?;
+    }
+
+    if options.error_format == ErrorFormat::Sarif {
+        let log = sarif::sarif_log(
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            &diagnostic.source_map(),
+            &sarif_sink.reports(),
+        );
+        match &options.sarif_output {
+            Some(path) => fs::write(path, format!("{:#}\n", log))?,
+            None => println!("{:#}", log),
+        }
+    }
+
+    if matches!(
+        options.error_format,
+        ErrorFormat::Human | ErrorFormat::Short
+    ) {
+        if let Some(summary) = diagnostic.summary() {
+            eprintln!("{}", summary);
+        }
+    }
+
+    if diagnostic.has_errors() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Compiles the input file. Returns the files that fix-its may be applied to.
+fn compile(
+    diagnostic: &Diagnostic,
+    options: &Options,
+    file_id: FileId,
+    source: &[u8],
+) -> Vec<FileId> {
+    let mut preprocessor = Preprocessor::new(diagnostic)
+        .with_standard(options.standard)
+        .with_include_paths(options.include_paths.clone());
+    let tokens = preprocessor.preprocess(file_id, source);
+    diagnostic.flush();
+
+    // Standard output only carries the SARIF log then
+    let sarif_to_stdout =
+        options.error_format == ErrorFormat::Sarif && options.sarif_output.is_none();
+    if !diagnostic.has_errors() && !sarif_to_stdout {
+        println!("Parsed tokens: {:#?}", tokens);
+    }
+
+    preprocessor.user_files().to_vec()
+}
+
+/// Applies the machine-applicable fix-its to the user files. System headers
+/// are never rewritten.
+fn apply_fixits(diagnostic: &Diagnostic, user_files: &[FileId]) -> Result<()> {
+    let fixits = diagnostic
+        .fixits()
+        .into_iter()
+        .filter(|fixit| fixit.applicability() == Applicability::MachineApplicable)
+        .collect::<Vec<_>>();
+
+    let source_map = diagnostic.source_map();
+    for &file_id in user_files {
+        let Some(file) = source_map.get(file_id) else {
+            continue;
+        };
+
+        // The source map holds the text with invalid UTF-8 replaced, whose
+        // offsets only match the original bytes up to the first invalid byte
+        let source = fs::read(file.name())?;
+        let valid_up_to = match std::str::from_utf8(&source) {
+            Ok(_) => source.len(),
+            Err(error) => error.valid_up_to(),
+        };
+
+        let file_fixits = fixits
+            .iter()
+            .filter(|fixit| fixit.span().file_id() == file_id)
+            .filter(|fixit| fixit.span().end() <= valid_up_to)
+            .cloned()
+            .collect::<Vec<_>>();
+        if file_fixits.is_empty() {
+            continue;
+        }
 
-    println!("Parsed tokens: {:#?}", tokens);
+        fs::write(file.name(), fixit::splice_fixits(&source, &file_fixits))?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,156 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use hyperc_diagnostics::{sink::ColorMode, warnings::WarningSettings};
+use hyperc_lexer::standard::Standard;
+use hyperc_preprocessor::include::IncludePaths;
+
+use color_eyre::{eyre::bail, Result};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Short,
+    Json,
+    Sarif,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "short" => Some(ErrorFormat::Short),
+            "json" => Some(ErrorFormat::Json),
+            "sarif" => Some(ErrorFormat::Sarif),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub input: Option<PathBuf>,
+    pub standard: Standard,
+    pub error_format: ErrorFormat,
+    /// The file the SARIF log is written to, instead of standard output.
+    pub sarif_output: Option<PathBuf>,
+    pub color: ColorMode,
+    /// The number of columns diagnostics are fitted into, where `0` means no
+    /// limit. The width of the terminal is detected if it is not set.
+    pub message_length: Option<usize>,
+    pub warnings_as_errors: bool,
+    pub fatal_errors: bool,
+    pub max_errors: usize,
+    pub warning_settings: WarningSettings,
+    pub include_paths: IncludePaths,
+    pub fixit: bool,
+    pub explain: Option<String>,
+}
+
+impl Options {
+    pub fn parse(arguments: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut options = Self::default();
+        let mut standard_includes = true;
+
+        let mut arguments = arguments.into_iter();
+        while let Some(argument) = arguments.next() {
+            if let Some(name) = argument.strip_prefix("-std=") {
+                let Some(standard) = Standard::from_name(name) else {
+                    bail!("unrecognized language standard `{}`", name);
+                };
+
+                options.standard = standard;
+            } else if let Some(name) = argument.strip_prefix("--error-format=") {
+                let Some(error_format) = ErrorFormat::from_name(name) else {
+                    bail!("unrecognized error format `{}`", name);
+                };
+
+                options.error_format = error_format;
+            } else if let Some(path) = argument.strip_prefix("--sarif-output=") {
+                options.sarif_output = Some(PathBuf::from(path));
+            } else if let Some(name) = argument.strip_prefix("--color=") {
+                let Some(color) = ColorMode::from_name(name) else {
+                    bail!("unrecognized color mode `{}`", name);
+                };
+
+                options.color = color;
+            } else if let Some(value) = argument.strip_prefix("-fmessage-length=") {
+                let Ok(message_length) = value.parse() else {
+                    bail!("invalid message length `{}`", value);
+                };
+
+                options.message_length = Some(message_length);
+            } else if argument == "--explain" {
+                let Some(code) = arguments.next() else {
+                    bail!("missing error code after `--explain`");
+                };
+
+                options.explain = Some(code);
+            } else if let Some(code) = argument.strip_prefix("--explain=") {
+                options.explain = Some(code.to_string());
+            } else if argument == "--fixit" {
+                options.fixit = true;
+            } else if argument == "-Werror" {
+                options.warnings_as_errors = true;
+            } else if argument == "-Wfatal-errors" {
+                options.fatal_errors = true;
+            } else if let Some(option) = argument.strip_prefix("-W") {
+                if !options.warning_settings.apply_option(option) {
+                    bail!("unrecognized warning option `{}`", argument);
+                }
+            } else if let Some(value) = argument.strip_prefix("-fmax-errors=") {
+                let Ok(max_errors) = value.parse() else {
+                    bail!("invalid error limit `{}`", value);
+                };
+
+                options.max_errors = max_errors;
+            } else if let Some(directory) = argument.strip_prefix("-iquote") {
+                let directory = directory_argument("-iquote", directory, &mut arguments)?;
+                options.include_paths.add_quote_directory(directory);
+            } else if let Some(directory) = argument.strip_prefix("-isystem") {
+                let directory = directory_argument("-isystem", directory, &mut arguments)?;
+                options.include_paths.add_system_directory(directory);
+            } else if let Some(directory) = argument.strip_prefix("-I") {
+                let directory = directory_argument("-I", directory, &mut arguments)?;
+                options.include_paths.add_directory(directory);
+            } else if argument == "-nostdinc" {
+                standard_includes = false;
+            } else if argument.starts_with('-') {
+                bail!("unrecognized command-line option `{}`", argument);
+            } else if options.input.is_none() {
+                options.input = Some(PathBuf::from(argument));
+            } else {
+                bail!("multiple input files are not supported");
+            }
+        }
+
+        if standard_includes {
+            options.include_paths = options.include_paths.with_default_system_directories();
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the directory of an option such as `-I`, which is either attached
+/// to it or the next argument.
+fn directory_argument(
+    option: &str,
+    directory: &str,
+    arguments: &mut impl Iterator<Item = String>,
+) -> Result<PathBuf> {
+    if !directory.is_empty() {
+        return Ok(PathBuf::from(directory));
+    }
+
+    let Some(directory) = arguments.next() else {
+        bail!("missing directory after `{}`", option);
+    };
+
+    Ok(PathBuf::from(directory))
+}
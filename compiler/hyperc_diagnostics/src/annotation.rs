@@ -6,7 +6,7 @@
 
 use hyperc_span::Span;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Style {
     Primary,
     Secondary,
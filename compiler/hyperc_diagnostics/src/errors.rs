@@ -4,22 +4,92 @@
  * SPDX-License-Identifier: MIT
  */
 
+use crate::registry;
+
 use std::fmt::{self, Display, Formatter};
 
-#[derive(Clone, Debug)]
-pub enum ErrorCode {
+/// The placeholder arguments of the error codes in [`ErrorCode::ALL`].
+trait Placeholder {
+    const PLACEHOLDER: Self;
+}
+
+impl Placeholder for String {
+    const PLACEHOLDER: Self = String::new();
+}
+
+impl Placeholder for char {
+    const PLACEHOLDER: Self = '\0';
+}
+
+/// Declares [`ErrorCode`] together with [`ErrorCode::ALL`] and
+/// [`ErrorCode::code`], so that no error code can be missing from either.
+macro_rules! error_codes {
+    ($($code:ident $(($($argument:ty),*))?,)*) => {
+        #[derive(Clone, Debug)]
+        pub enum ErrorCode {
+            $($code $(($($argument),*))?,)*
+        }
+
+        impl ErrorCode {
+            /// One instance of every error code, with placeholder arguments.
+            pub const ALL: &'static [ErrorCode] = &[
+                $(ErrorCode::$code $(($(<$argument as Placeholder>::PLACEHOLDER),*))?,)*
+            ];
+
+            pub fn code(&self) -> &'static str {
+                match *self {
+                    $(ErrorCode::$code { .. } => stringify!($code),)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
     E0001(String),
     E0002,
     E0003,
+    E0004,
+    E0005,
+    E0006(char),
+    E0007,
+    E0008,
+    E0009,
+    E0010,
+    E0011,
+    E0012,
+    E0013,
+    E0014,
+    E0015,
+    E0016,
+    E0017(char),
+    E0018(String),
+    E0019,
+    E0020,
+    E0021,
+    E0022,
+    E0023(String),
+    E0024,
+    E0025(String),
+    E0026(String),
+    E0027(String, String),
+    E0028(String),
+    E0029,
+    E0030(String),
+    E0031(String),
+    E0032(String),
+    E0033(String),
+    E0034,
+    E0035,
+    E0036,
+    E0037(String),
+    E0038,
+    E0039(String),
 }
 
 impl ErrorCode {
-    pub fn code(&self) -> &'static str {
-        match *self {
-            ErrorCode::E0001(_) => "E0001",
-            ErrorCode::E0002 => "E0002",
-            ErrorCode::E0003 => "E0003",
-        }
+    pub fn explanation(&self) -> &'static str {
+        registry::explanation(self.code()).unwrap_or_default()
     }
 }
 
@@ -29,6 +99,63 @@ impl Display for ErrorCode {
             ErrorCode::E0001(ref token) => write!(f, "unexpected token, found `{}`", token),
             ErrorCode::E0002 => write!(f, "unclosed hexadecimal number"),
             ErrorCode::E0003 => write!(f, "unclosed string literal"),
+            ErrorCode::E0004 => write!(f, "unclosed character constant"),
+            ErrorCode::E0005 => write!(f, "empty character constant"),
+            ErrorCode::E0006(character) => {
+                write!(
+                    f,
+                    "unknown escape sequence `\\{}`",
+                    character.escape_default()
+                )
+            }
+            ErrorCode::E0007 => write!(f, "hexadecimal escape sequence without digits"),
+            ErrorCode::E0008 => write!(f, "escape sequence out of range"),
+            ErrorCode::E0009 => write!(f, "invalid universal character name"),
+            ErrorCode::E0010 => write!(f, "exponent has no digits"),
+            ErrorCode::E0011 => write!(f, "hexadecimal floating constant requires an exponent"),
+            ErrorCode::E0012 => write!(f, "unterminated block comment"),
+            ErrorCode::E0013 => write!(f, "source file is not valid UTF-8"),
+            ErrorCode::E0014 => write!(f, "integer constant is too large for its type"),
+            ErrorCode::E0015 => write!(f, "malformed integer constant"),
+            ErrorCode::E0016 => write!(f, "invalid digit separator"),
+            ErrorCode::E0017(digit) => write!(f, "invalid digit `{}` in octal constant", digit),
+            ErrorCode::E0018(ref directive) => {
+                write!(f, "invalid preprocessing directive `#{}`", directive)
+            }
+            ErrorCode::E0019 => write!(f, "invalid macro name"),
+            ErrorCode::E0020 => write!(f, "invalid macro parameter list"),
+            ErrorCode::E0021 => write!(f, "`#` is not followed by a macro parameter"),
+            ErrorCode::E0022 => write!(f, "`##` cannot appear at either end of a macro expansion"),
+            ErrorCode::E0023(ref identifier) => {
+                write!(f, "`{}` can only appear in a variadic macro", identifier)
+            }
+            ErrorCode::E0024 => write!(f, "invalid use of `__VA_OPT__`"),
+            ErrorCode::E0025(ref name) => {
+                write!(f, "unterminated argument list invoking macro `{}`", name)
+            }
+            ErrorCode::E0026(ref name) => {
+                write!(f, "wrong number of arguments for macro `{}`", name)
+            }
+            ErrorCode::E0027(ref lhs, ref rhs) => write!(
+                f,
+                "pasting `{}` and `{}` does not give a valid preprocessing token",
+                lhs, rhs
+            ),
+            ErrorCode::E0028(ref name) => write!(f, "`{}` file not found", name),
+            ErrorCode::E0029 => write!(f, "expected `\"FILENAME\"` or `<FILENAME>`"),
+            ErrorCode::E0030(ref name) => write!(f, "`{}` is included recursively", name),
+            ErrorCode::E0031(ref directive) => write!(f, "`#{}` without `#if`", directive),
+            ErrorCode::E0032(ref directive) => write!(f, "unterminated `#{}`", directive),
+            ErrorCode::E0033(ref directive) => write!(f, "`#{}` after `#else`", directive),
+            ErrorCode::E0034 => write!(f, "invalid preprocessing condition"),
+            ErrorCode::E0035 => write!(f, "division by zero in preprocessing condition"),
+            ErrorCode::E0036 => write!(f, "invalid suffix on numeric constant"),
+            ErrorCode::E0037(ref message) if message.is_empty() => write!(f, "#error"),
+            ErrorCode::E0037(ref message) => write!(f, "#error {}", message),
+            ErrorCode::E0038 => write!(f, "invalid `#line` directive"),
+            ErrorCode::E0039(ref directive) => {
+                write!(f, "unsupported preprocessing directive `#{}`", directive)
+            }
         }
     }
 }
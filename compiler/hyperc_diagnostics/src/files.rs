@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use hyperc_span::{
+    source_map::{SourceFile, SourceMap},
+    FileId, Span,
+};
+
+use codespan_reporting::files::{Error, Files};
+use std::ops::Range;
+
+/// Exposes a [`SourceMap`] to `codespan-reporting`, with the line numbers and
+/// file names set by `#line` directives.
+pub(crate) struct SourceFiles<'a> {
+    source_map: &'a SourceMap,
+    /// The file name at the primary span of the report, which is shown for
+    /// every span in its file.
+    presumed_name: Option<(FileId, &'a str)>,
+}
+
+impl<'a> SourceFiles<'a> {
+    pub(crate) fn new(source_map: &'a SourceMap, primary_span: Option<Span>) -> Self {
+        let presumed_name = primary_span.and_then(|span| {
+            let file = source_map.get(span.file_id())?;
+            Some((span.file_id(), file.presumed_name(span.start())))
+        });
+
+        Self {
+            source_map,
+            presumed_name,
+        }
+    }
+
+    fn file(&self, file_id: FileId) -> Result<&'a SourceFile, Error> {
+        self.source_map.get(file_id).ok_or(Error::FileMissing)
+    }
+}
+
+impl<'a> Files<'a> for SourceFiles<'_> {
+    type FileId = FileId;
+    type Name = &'a str;
+    type Source = &'a str;
+
+    fn name(&'a self, file_id: FileId) -> Result<Self::Name, Error> {
+        match self.presumed_name {
+            Some((presumed_file_id, name)) if presumed_file_id == file_id => Ok(name),
+            _ => self.file(file_id).map(SourceFile::name),
+        }
+    }
+
+    fn source(&'a self, file_id: FileId) -> Result<Self::Source, Error> {
+        self.file(file_id).map(SourceFile::source)
+    }
+
+    fn line_index(&'a self, file_id: FileId, byte_index: usize) -> Result<usize, Error> {
+        Ok(self.file(file_id)?.line_index(byte_index))
+    }
+
+    fn line_number(&'a self, file_id: FileId, line_index: usize) -> Result<usize, Error> {
+        Ok(self.file(file_id)?.presumed_line(line_index))
+    }
+
+    fn line_range(&'a self, file_id: FileId, line_index: usize) -> Result<Range<usize>, Error> {
+        let file = self.file(file_id)?;
+        if line_index >= file.line_count() {
+            return Err(Error::LineTooLarge {
+                given: line_index,
+                max: file.line_count() - 1,
+            });
+        }
+
+        Ok(file.line_range(line_index))
+    }
+}
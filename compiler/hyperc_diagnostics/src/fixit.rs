@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use hyperc_span::Span;
+
+use std::fmt::{self, Display, Formatter};
+
+/// How confident a fix-it is that applying it gives the intended code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Applicability {
+    /// The fix-it is definitely correct and can be applied automatically.
+    MachineApplicable,
+    /// The fix-it may not be what the user intended.
+    MaybeIncorrect,
+    /// The replacement contains placeholders the user has to fill in.
+    HasPlaceholders,
+    Unspecified,
+}
+
+impl Applicability {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+            Applicability::HasPlaceholders => "has-placeholders",
+            Applicability::Unspecified => "unspecified",
+        }
+    }
+}
+
+/// Replaces the text of a span. An empty span inserts the replacement and an
+/// empty replacement removes the span.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixIt {
+    span: Span,
+    replacement: String,
+    applicability: Applicability,
+}
+
+impl FixIt {
+    pub fn new(span: Span, replacement: impl ToString, applicability: Applicability) -> Self {
+        Self {
+            span,
+            replacement: replacement.to_string(),
+            applicability,
+        }
+    }
+
+    pub fn insertion(span: Span, text: impl ToString, applicability: Applicability) -> Self {
+        Self::new(span.shrink_to_lo(), text, applicability)
+    }
+
+    pub fn removal(span: Span, applicability: Applicability) -> Self {
+        Self::new(span, "", applicability)
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    pub fn applicability(&self) -> Applicability {
+        self.applicability
+    }
+}
+
+impl Display for FixIt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.span.is_empty() {
+            write!(f, "insert `{}`", self.replacement)
+        } else if self.replacement.is_empty() {
+            write!(f, "remove this code")
+        } else {
+            write!(f, "replace with `{}`", self.replacement)
+        }
+    }
+}
+
+/// Applies the fix-its of one file to its source. Fix-its overlapping an
+/// earlier one are skipped.
+pub fn apply_fixits(source: &str, fixits: &[FixIt]) -> String {
+    String::from_utf8_lossy(&splice_fixits(source.as_bytes(), fixits)).into_owned()
+}
+
+/// Applies the fix-its of one file to its original bytes, which may not be
+/// valid UTF-8. Fix-its overlapping an earlier one are skipped.
+pub fn splice_fixits(source: &[u8], fixits: &[FixIt]) -> Vec<u8> {
+    let mut fixits = fixits.iter().collect::<Vec<_>>();
+    fixits.sort_by_key(|fixit| fixit.span);
+
+    let mut output = Vec::with_capacity(source.len());
+    let mut index = 0;
+    for fixit in fixits {
+        let span = fixit.span;
+        if span.start() < index || span.end() > source.len() {
+            continue;
+        }
+
+        output.extend_from_slice(&source[index..span.start()]);
+        output.extend_from_slice(fixit.replacement.as_bytes());
+        index = span.end();
+    }
+
+    output.extend_from_slice(&source[index..]);
+    output
+}
@@ -0,0 +1,157 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{annotation::Annotation, report::Report};
+
+use hyperc_span::Span;
+
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+    thread,
+};
+
+static HOOK: Once = Once::new();
+
+thread_local! {
+    static STAGE: Cell<Option<&'static str>> = const { Cell::new(None) };
+    static SPAN: Cell<Option<Span>> = const { Cell::new(None) };
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks `stage` as running until the guard is dropped, so an internal
+/// compiler error can name it.
+pub fn enter_stage(stage: &'static str) -> StageGuard {
+    StageGuard {
+        stage: STAGE.replace(Some(stage)),
+        span: SPAN.take(),
+    }
+}
+
+/// Records the span the running stage is processing.
+pub fn set_current_span(span: Span) {
+    SPAN.set(Some(span));
+}
+
+#[must_use]
+pub struct StageGuard {
+    stage: Option<&'static str>,
+    span: Option<Span>,
+}
+
+impl Drop for StageGuard {
+    fn drop(&mut self) {
+        // A panic keeps the stage and span it happened in for `Ice::from_panic`
+        if thread::panicking() {
+            return;
+        }
+
+        STAGE.set(self.stage);
+        SPAN.set(self.span);
+    }
+}
+
+/// A panic caught by [`catch_ice`].
+#[derive(Clone, Debug)]
+pub struct Ice {
+    message: String,
+    location: Option<String>,
+    stage: Option<&'static str>,
+    span: Option<Span>,
+}
+
+impl Ice {
+    /// Builds the internal compiler error of a panic caught on this thread,
+    /// with the stage and span it happened in.
+    pub fn from_panic(payload: &(dyn Any + Send)) -> Self {
+        Self {
+            message: panic_message(payload),
+            location: LOCATION.take(),
+            stage: STAGE.take(),
+            span: SPAN.take(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    pub fn stage(&self) -> Option<&'static str> {
+        self.stage
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn to_report(&self, version: &str) -> Report {
+        let mut report = Report::bug()
+            .with_message(format!("internal compiler error: {}", self.message))
+            .with_note(format!("hyperc {} unexpectedly panicked", version));
+
+        if let Some(stage) = self.stage {
+            report = report.with_note(format!("the crash happened while {}", stage));
+        }
+
+        if let Some(location) = &self.location {
+            report = report.with_note(format!("panicked at {}", location));
+        }
+
+        if let Some(span) = self.span {
+            report = report.with_annotations(vec![Annotation::primary(
+                span,
+                "the compiler crashed while processing this",
+            )]);
+        }
+
+        report.with_help("this is a bug in hyperc, please file a report with a reduced reproducer")
+    }
+}
+
+/// Runs `f` and turns a panic inside it into an [`Ice`]. While `f` runs, the
+/// panic hook only records where the panic happened instead of printing it.
+pub fn catch_ice<R>(f: impl FnOnce() -> R) -> Result<R, Ice> {
+    install_hook();
+
+    CATCHING.set(CATCHING.get() + 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(CATCHING.get() - 1);
+
+    result.map_err(|payload| Ice::from_panic(payload.as_ref()))
+}
+
+/// Installs the panic hook once for the whole process. Panics on threads that
+/// are not inside [`catch_ice`] still go to the previous hook.
+fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.get() == 0 {
+                previous(info);
+                return;
+            }
+
+            LOCATION.set(info.location().map(ToString::to_string));
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
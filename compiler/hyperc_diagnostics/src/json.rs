@@ -0,0 +1,90 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{
+    annotation::{Annotation, Style},
+    fixit::FixIt,
+    report::Report,
+};
+
+use hyperc_span::{source_map::SourceMap, Span};
+
+use serde_json::{json, Value};
+
+/// Converts a report into a JSON object with 1-based line and column ranges
+/// for every annotation.
+pub fn report_to_json(source_map: &SourceMap, report: &Report) -> Value {
+    let annotations = report
+        .annotations()
+        .iter()
+        .map(|annotation| annotation_to_json(source_map, annotation))
+        .collect::<Vec<_>>();
+
+    let children = report
+        .children()
+        .iter()
+        .map(|child| {
+            json!({
+                "kind": child.kind().name(),
+                "message": child.message(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let fixits = report
+        .fixits()
+        .iter()
+        .map(|fixit| fixit_to_json(source_map, fixit))
+        .collect::<Vec<_>>();
+
+    json!({
+        "severity": report.severity().name(),
+        "code": report.code(),
+        "message": report.message(),
+        "warning": report.named_warning().map(|warning| warning.name()),
+        "annotations": annotations,
+        "children": children,
+        "fixits": fixits,
+    })
+}
+
+fn annotation_to_json(source_map: &SourceMap, annotation: &Annotation) -> Value {
+    let mut value = span_to_json(source_map, annotation.span());
+    value["is_primary"] = json!(matches!(annotation.style(), Style::Primary));
+    value["message"] = json!(annotation.message());
+    value
+}
+
+fn fixit_to_json(source_map: &SourceMap, fixit: &FixIt) -> Value {
+    let mut value = span_to_json(source_map, fixit.span());
+    value["replacement"] = json!(fixit.replacement());
+    value["applicability"] = json!(fixit.applicability().name());
+    value
+}
+
+fn span_to_json(source_map: &SourceMap, span: Span) -> Value {
+    let file = source_map.get(span.file_id());
+    let (start, end) = match file {
+        Some(file) => {
+            let (start, end) = file.span_location(span);
+            (
+                Some((start.line(), start.column())),
+                Some((end.line(), end.column())),
+            )
+        }
+        None => (None, None),
+    };
+
+    json!({
+        "file_name": file.map(|file| file.name()),
+        "byte_start": span.start(),
+        "byte_end": span.end(),
+        "line_start": start.map(|(line, _)| line),
+        "line_end": end.map(|(line, _)| line),
+        "column_start": start.map(|(_, column)| column),
+        "column_end": end.map(|(_, column)| column),
+    })
+}
@@ -6,80 +6,392 @@
 
 pub mod annotation;
 pub mod errors;
+mod files;
+pub mod fixit;
+pub mod ice;
+pub mod json;
+pub mod registry;
 pub mod report;
+pub mod sarif;
+pub mod sink;
+mod tests;
+pub mod warnings;
 
 use crate::{
-    annotation::Style,
+    fixit::FixIt,
     report::{Report, Severity},
+    sink::{DiagnosticSink, TerminalSink},
+    warnings::{WarningLevel, WarningSettings},
 };
 
-use codespan_reporting::{
-    diagnostic::{self, LabelStyle},
-    files::SimpleFiles,
-    term::{
-        self,
-        termcolor::{ColorChoice, StandardStream},
-        Config,
-    },
+use hyperc_span::{source_map::SourceMap, FileId};
+
+use std::{
+    cell::{Cell, Ref, RefCell},
+    collections::HashMap,
 };
 
+/// Marks the position in the speculative reports where a speculative stage
+/// started.
+#[must_use]
+#[derive(Debug)]
+pub struct Checkpoint {
+    index: usize,
+}
+
 #[derive(Debug)]
 pub struct Diagnostic {
-    files: SimpleFiles<String, String>,
-    file_id: usize,
+    source_map: RefCell<SourceMap>,
+    sink: RefCell<Box<dyn DiagnosticSink>>,
+
+    buffered: bool,
+    buffer: RefCell<Vec<Report>>,
+    /// The reports of the running speculative stages, which are neither
+    /// counted nor emitted before the outermost stage is committed.
+    speculative: RefCell<Vec<Report>>,
+    speculations: Cell<usize>,
+
+    counts: RefCell<HashMap<Severity, usize>>,
+    fixits: RefCell<Vec<FixIt>>,
+    warning_settings: RefCell<Vec<WarningSettings>>,
+    warnings_as_errors: bool,
+    fatal_errors: bool,
+    max_errors: usize,
 }
 
 impl Diagnostic {
-    pub fn new(file: impl ToString, source: impl ToString) -> Self {
-        let mut files = SimpleFiles::new();
-        let file_id = files.add(file.to_string(), source.to_string());
+    pub fn new() -> Self {
+        Self {
+            source_map: RefCell::new(SourceMap::new()),
+            sink: RefCell::new(Box::new(TerminalSink::default())),
+
+            buffered: false,
+            buffer: RefCell::new(Vec::new()),
+            speculative: RefCell::new(Vec::new()),
+            speculations: Cell::new(0),
 
-        Self { files, file_id }
+            counts: RefCell::new(HashMap::new()),
+            fixits: RefCell::new(Vec::new()),
+            warning_settings: RefCell::new(vec![WarningSettings::default()]),
+            warnings_as_errors: false,
+            fatal_errors: false,
+            max_errors: 0,
+        }
     }
 
-    pub fn report(&self, report: Report) {
-        let Report {
-            severity,
-            message,
-            code,
-            annotations,
-        } = report;
+    /// Sends every report to `sink` instead of the terminal.
+    pub fn with_sink(mut self, sink: impl DiagnosticSink + 'static) -> Self {
+        self.sink = RefCell::new(Box::new(sink));
+        self
+    }
 
-        let severity = match severity {
-            Severity::Note => diagnostic::Severity::Note,
-            Severity::Warning => diagnostic::Severity::Warning,
-            Severity::Error => diagnostic::Severity::Error,
-        };
+    /// Collects reports until [`flush`](Self::flush) instead of emitting them
+    /// right away.
+    pub fn with_buffering(mut self, buffered: bool) -> Self {
+        self.buffered = buffered;
+        self
+    }
+
+    pub fn with_warning_settings(self, warning_settings: WarningSettings) -> Self {
+        *self.warning_settings.borrow_mut() = vec![warning_settings];
+        self
+    }
+
+    /// Reports every warning as an error, like `-Werror`.
+    pub fn with_warnings_as_errors(mut self, warnings_as_errors: bool) -> Self {
+        self.warnings_as_errors = warnings_as_errors;
+        self
+    }
+
+    /// Stops reporting after the first error, like `-Wfatal-errors`.
+    pub fn with_fatal_errors(mut self, fatal_errors: bool) -> Self {
+        self.fatal_errors = fatal_errors;
+        self
+    }
+
+    /// Stops reporting after `max_errors` errors, like `-fmax-errors=`. A limit
+    /// of 0 means no limit.
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Registers a source file so reports can annotate spans inside it.
+    pub fn add_file(&self, name: impl ToString, source: impl ToString) -> FileId {
+        self.source_map.borrow_mut().add(name, source)
+    }
+
+    /// Appends `text` to a registered source file and returns the offset it
+    /// starts at, or `None` if the file is unknown.
+    pub fn append_to_file(&self, file_id: FileId, text: &str) -> Option<usize> {
+        self.source_map
+            .borrow_mut()
+            .get_mut(file_id)
+            .map(|file| file.append(text))
+    }
+
+    /// Applies a `#line` directive to a registered source file, as described by
+    /// [`SourceFile::add_line_directive`](hyperc_span::source_map::SourceFile::add_line_directive).
+    pub fn add_line_directive(
+        &self,
+        file_id: FileId,
+        offset: usize,
+        line: usize,
+        name: Option<String>,
+    ) {
+        if let Some(file) = self.source_map.borrow_mut().get_mut(file_id) {
+            file.add_line_directive(offset, line, name);
+        }
+    }
+
+    pub fn source_map(&self) -> Ref<'_, SourceMap> {
+        self.source_map.borrow()
+    }
+
+    pub fn report(&self, mut report: Report) {
+        if self.has_reached_error_limit() {
+            return;
+        }
+
+        if let Some(warning) = report.warning {
+            match self.warning_settings().level(warning) {
+                WarningLevel::Ignored => return,
+                WarningLevel::Warning => {}
+                WarningLevel::Error => report.severity = Severity::Error,
+            }
+        }
+
+        if self.warnings_as_errors && report.severity == Severity::Warning {
+            report.severity = Severity::Error;
+        }
+
+        let is_duplicate = self
+            .buffer
+            .borrow()
+            .iter()
+            .chain(self.speculative.borrow().iter())
+            .any(|buffered| buffered.is_duplicate_of(&report));
+        if is_duplicate {
+            return;
+        }
+
+        if self.speculations.get() > 0 {
+            self.speculative.borrow_mut().push(report);
+        } else {
+            self.dispatch(report);
+        }
+    }
+
+    /// Reports an internal compiler error. Unlike [`report`](Self::report), it
+    /// is never dropped by the error limit, and the reports of the speculative
+    /// stages the crash left unfinished are discarded.
+    pub fn report_ice(&self, report: Report) {
+        self.speculative.borrow_mut().clear();
+        self.speculations.set(0);
+        self.send(report);
+    }
+
+    /// Emits every buffered report once, sorted by file and position. Reports
+    /// without a primary annotation come last. The reports of running
+    /// speculative stages are kept.
+    pub fn flush(&self) {
+        let mut reports = self.buffer.take();
+        sort_reports(&mut reports);
+
+        for report in reports {
+            self.emit(report);
+        }
+    }
+
+    /// Starts a speculative stage. Its reports are held back until they are
+    /// kept with [`commit`](Self::commit) or dropped with
+    /// [`discard`](Self::discard), and do not count towards the error limit
+    /// before that.
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.speculations.set(self.speculations.get() + 1);
+
+        Checkpoint {
+            index: self.speculative.borrow().len(),
+        }
+    }
+
+    /// Keeps the reports of a speculative stage. They are counted and emitted
+    /// once the outermost speculative stage is committed.
+    pub fn commit(&self, _checkpoint: Checkpoint) {
+        self.speculations
+            .set(self.speculations.get().saturating_sub(1));
+        if self.speculations.get() > 0 {
+            return;
+        }
+
+        let mut reports = self.speculative.take();
+        if !self.buffered {
+            sort_reports(&mut reports);
+        }
+
+        for report in reports {
+            self.dispatch(report);
+        }
+    }
+
+    /// Drops every report made since `checkpoint`.
+    pub fn discard(&self, checkpoint: Checkpoint) {
+        let mut speculative = self.speculative.borrow_mut();
+        let index = checkpoint.index.min(speculative.len());
+        speculative.truncate(index);
+
+        self.speculations
+            .set(self.speculations.get().saturating_sub(1));
+    }
+
+    pub fn warning_settings(&self) -> Ref<'_, WarningSettings> {
+        Ref::map(self.warning_settings.borrow(), |stack| {
+            stack.last().unwrap()
+        })
+    }
+
+    /// Handles the arguments of `#pragma hyperc diagnostic`, which are `push`,
+    /// `pop`, or one of `ignored`, `warning` and `error` followed by a quoted
+    /// `-W` option. Returns `false` if the pragma is malformed.
+    pub fn apply_pragma(&self, arguments: &str) -> bool {
+        let mut stack = self.warning_settings.borrow_mut();
+
+        let arguments = arguments.split_whitespace().collect::<Vec<_>>();
+        match arguments.as_slice() {
+            ["push"] => {
+                let settings = stack.last().unwrap().clone();
+                stack.push(settings);
+                true
+            }
+            ["pop"] => stack.len() > 1 && stack.pop().is_some(),
+            [kind, option] => {
+                let Some(name) = option
+                    .strip_prefix("\"-W")
+                    .and_then(|option| option.strip_suffix('"'))
+                else {
+                    return false;
+                };
+
+                let option = match *kind {
+                    "ignored" => format!("no-{}", name),
+                    "warning" => name.to_string(),
+                    "error" => format!("error={}", name),
+                    _ => return false,
+                };
+
+                let settings = stack.last_mut().unwrap();
+                settings.apply_option(&option)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the fix-its of every report emitted so far.
+    pub fn fixits(&self) -> Vec<FixIt> {
+        self.fixits.borrow().clone()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.counts
+            .borrow()
+            .get(&severity)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
 
-        let mut diagnostic = diagnostic::Diagnostic::new(severity)
-            .with_message(message.unwrap())
-            .with_labels(
-                annotations
-                    .iter()
-                    .map(|annotation| {
-                        let style = match annotation.style() {
-                            Style::Primary => LabelStyle::Primary,
-                            Style::Secondary => LabelStyle::Secondary,
-                        };
-                        diagnostic::Label::new(
-                            style,
-                            self.file_id,
-                            annotation.span().start()..annotation.span().end(),
-                        )
-                        .with_message(annotation.message())
-                    })
-                    .collect(),
-            );
-
-        if code.is_some() {
-            diagnostic = diagnostic.with_code(code.unwrap());
-        }
-
-        let writer = StandardStream::stderr(ColorChoice::Always);
-        let config = Config {
-            ..Default::default()
+    /// Returns whether an error or an internal compiler error was reported.
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0 || self.count(Severity::Bug) > 0
+    }
+
+    /// Returns whether the error limit was reached, after which every further
+    /// report is dropped and stages should stop early.
+    pub fn has_reached_error_limit(&self) -> bool {
+        let limit = if self.fatal_errors {
+            1
+        } else {
+            self.max_errors
         };
+        limit > 0 && self.error_count() >= limit
+    }
+
+    /// Returns a summary like `3 errors, 2 warnings generated`, or `None` if
+    /// nothing was reported.
+    pub fn summary(&self) -> Option<String> {
+        let counts = [
+            (self.error_count(), "error"),
+            (self.warning_count(), "warning"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| match count {
+            1 => format!("1 {}", name),
+            _ => format!("{} {}s", count, name),
+        })
+        .collect::<Vec<_>>();
+
+        if counts.is_empty() {
+            return None;
+        }
+
+        Some(format!("{} generated", counts.join(", ")))
+    }
+
+    /// Counts and sends a report that is no longer speculative, unless the
+    /// error limit was reached in the meantime.
+    fn dispatch(&self, report: Report) {
+        if self.has_reached_error_limit() {
+            return;
+        }
+
+        let severity = report.severity;
+        self.send(report);
+
+        if severity == Severity::Error && self.has_reached_error_limit() {
+            let reason = if self.fatal_errors {
+                String::from("-Wfatal-errors")
+            } else {
+                format!("-fmax-errors={}", self.max_errors)
+            };
+
+            let note =
+                Report::note().with_message(format!("compilation terminated due to {}", reason));
+            self.send(note);
+        }
+    }
+
+    fn send(&self, report: Report) {
+        *self.counts.borrow_mut().entry(report.severity).or_default() += 1;
+
+        if self.buffered {
+            self.buffer.borrow_mut().push(report);
+        } else {
+            self.emit(report);
+        }
+    }
+
+    fn emit(&self, report: Report) {
+        self.fixits.borrow_mut().extend_from_slice(&report.fixits);
+
+        let source_map = self.source_map();
+        self.sink.borrow_mut().emit(&source_map, report);
+    }
+}
+
+fn sort_reports(reports: &mut [Report]) {
+    reports.sort_by_key(|report| (report.primary_span().is_none(), report.primary_span()));
+}
 
-        term::emit(&mut writer.lock(), &config, &self.files, &diagnostic).unwrap();
+impl Default for Diagnostic {
+    fn default() -> Self {
+        Self::new()
     }
 }
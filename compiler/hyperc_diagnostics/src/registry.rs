@@ -0,0 +1,56 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+/// The long Markdown explanation of every error code, as printed by
+/// `hyperc --explain`.
+pub const EXPLANATIONS: &[(&str, &str)] = &[
+    ("E0001", include_str!("../explanations/E0001.md")),
+    ("E0002", include_str!("../explanations/E0002.md")),
+    ("E0003", include_str!("../explanations/E0003.md")),
+    ("E0004", include_str!("../explanations/E0004.md")),
+    ("E0005", include_str!("../explanations/E0005.md")),
+    ("E0006", include_str!("../explanations/E0006.md")),
+    ("E0007", include_str!("../explanations/E0007.md")),
+    ("E0008", include_str!("../explanations/E0008.md")),
+    ("E0009", include_str!("../explanations/E0009.md")),
+    ("E0010", include_str!("../explanations/E0010.md")),
+    ("E0011", include_str!("../explanations/E0011.md")),
+    ("E0012", include_str!("../explanations/E0012.md")),
+    ("E0013", include_str!("../explanations/E0013.md")),
+    ("E0014", include_str!("../explanations/E0014.md")),
+    ("E0015", include_str!("../explanations/E0015.md")),
+    ("E0016", include_str!("../explanations/E0016.md")),
+    ("E0017", include_str!("../explanations/E0017.md")),
+    ("E0018", include_str!("../explanations/E0018.md")),
+    ("E0019", include_str!("../explanations/E0019.md")),
+    ("E0020", include_str!("../explanations/E0020.md")),
+    ("E0021", include_str!("../explanations/E0021.md")),
+    ("E0022", include_str!("../explanations/E0022.md")),
+    ("E0023", include_str!("../explanations/E0023.md")),
+    ("E0024", include_str!("../explanations/E0024.md")),
+    ("E0025", include_str!("../explanations/E0025.md")),
+    ("E0026", include_str!("../explanations/E0026.md")),
+    ("E0027", include_str!("../explanations/E0027.md")),
+    ("E0028", include_str!("../explanations/E0028.md")),
+    ("E0029", include_str!("../explanations/E0029.md")),
+    ("E0030", include_str!("../explanations/E0030.md")),
+    ("E0031", include_str!("../explanations/E0031.md")),
+    ("E0032", include_str!("../explanations/E0032.md")),
+    ("E0033", include_str!("../explanations/E0033.md")),
+    ("E0034", include_str!("../explanations/E0034.md")),
+    ("E0035", include_str!("../explanations/E0035.md")),
+    ("E0036", include_str!("../explanations/E0036.md")),
+    ("E0037", include_str!("../explanations/E0037.md")),
+    ("E0038", include_str!("../explanations/E0038.md")),
+    ("E0039", include_str!("../explanations/E0039.md")),
+];
+
+pub fn explanation(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(name, _)| *name == code)
+        .map(|(_, explanation)| *explanation)
+}
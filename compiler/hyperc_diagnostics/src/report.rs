@@ -4,13 +4,67 @@
  * SPDX-License-Identifier: MIT
  */
 
-use crate::{annotation::Annotation, errors::ErrorCode};
+use crate::{
+    annotation::{Annotation, Style},
+    errors::ErrorCode,
+    fixit::FixIt,
+    warnings::Warning,
+};
 
-#[derive(Clone, Copy, Debug)]
+use hyperc_span::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Severity {
+    Help,
     Note,
     Warning,
     Error,
+    /// An internal compiler error.
+    Bug,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Severity::Help => "help",
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Bug => "bug",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChildKind {
+    Note,
+    Help,
+}
+
+impl ChildKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ChildKind::Note => "note",
+            ChildKind::Help => "help",
+        }
+    }
+}
+
+/// A note or help message shown below its parent report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Child {
+    kind: ChildKind,
+    message: String,
+}
+
+impl Child {
+    pub fn kind(&self) -> ChildKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -19,6 +73,9 @@ pub struct Report {
     pub(crate) message: Option<String>,
     pub(crate) code: Option<String>,
     pub(crate) annotations: Vec<Annotation>,
+    pub(crate) warning: Option<Warning>,
+    pub(crate) children: Vec<Child>,
+    pub(crate) fixits: Vec<FixIt>,
 }
 
 impl Report {
@@ -28,9 +85,16 @@ impl Report {
             message: None,
             code: None,
             annotations: Vec::new(),
+            warning: None,
+            children: Vec::new(),
+            fixits: Vec::new(),
         }
     }
 
+    pub fn help() -> Self {
+        Self::new(Severity::Help)
+    }
+
     pub fn note() -> Self {
         Self::new(Severity::Note)
     }
@@ -43,6 +107,10 @@ impl Report {
         Self::new(Severity::Error)
     }
 
+    pub fn bug() -> Self {
+        Self::new(Severity::Bug)
+    }
+
     pub fn with_message(mut self, message: impl ToString) -> Self {
         self.message = Some(message.to_string());
         self.code = None;
@@ -59,4 +127,78 @@ impl Report {
         self.annotations = annotations;
         self
     }
+
+    /// Names the warning that controls this report.
+    pub fn with_warning(mut self, warning: Warning) -> Self {
+        self.warning = Some(warning);
+        self
+    }
+
+    pub fn with_note(mut self, message: impl ToString) -> Self {
+        self.children.push(Child {
+            kind: ChildKind::Note,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_help(mut self, message: impl ToString) -> Self {
+        self.children.push(Child {
+            kind: ChildKind::Help,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_fixit(mut self, fixit: FixIt) -> Self {
+        self.fixits.push(fixit);
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn named_warning(&self) -> Option<Warning> {
+        self.warning
+    }
+
+    pub fn children(&self) -> &[Child] {
+        &self.children
+    }
+
+    pub fn fixits(&self) -> &[FixIt] {
+        &self.fixits
+    }
+
+    /// Returns the span of the first primary annotation.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.annotations
+            .iter()
+            .find(|annotation| matches!(annotation.style(), Style::Primary))
+            .map(Annotation::span)
+    }
+
+    /// Returns whether both reports have the same code, or the same message if
+    /// they have no code, and the same primary span.
+    pub fn is_duplicate_of(&self, other: &Report) -> bool {
+        let identity = |report: &Report| match &report.code {
+            Some(code) => (Some(code.clone()), None),
+            None => (None, report.message.clone()),
+        };
+
+        identity(self) == identity(other) && self.primary_span() == other.primary_span()
+    }
 }
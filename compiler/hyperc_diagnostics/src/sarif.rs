@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{
+    annotation::{Annotation, Style},
+    fixit::FixIt,
+    report::{Report, Severity},
+};
+
+use hyperc_span::source_map::SourceMap;
+
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Builds a SARIF 2.1.0 log with a single run of the tool `name`.
+pub fn sarif_log(name: &str, version: &str, source_map: &SourceMap, reports: &[Report]) -> Value {
+    let rules = reports
+        .iter()
+        .filter_map(rule_id)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|code| json!({ "id": code }))
+        .collect::<Vec<_>>();
+
+    let results = reports
+        .iter()
+        .map(|report| result(source_map, report))
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": name,
+                    "version": version,
+                    "rules": rules,
+                },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    })
+}
+
+fn result(source_map: &SourceMap, report: &Report) -> Value {
+    let (primary, secondary): (Vec<_>, Vec<_>) = report
+        .annotations()
+        .iter()
+        .partition(|annotation| matches!(annotation.style(), Style::Primary));
+
+    let mut result = json!({
+        "level": level(report.severity()),
+        "message": { "text": report.message().unwrap_or_default() },
+        "locations": primary
+            .iter()
+            .map(|annotation| location(source_map, annotation))
+            .collect::<Vec<_>>(),
+        "relatedLocations": secondary
+            .iter()
+            .map(|annotation| location(source_map, annotation))
+            .collect::<Vec<_>>(),
+    });
+
+    if let Some(rule_id) = rule_id(report) {
+        result["ruleId"] = json!(rule_id);
+    }
+
+    if !report.fixits().is_empty() {
+        result["fixes"] = report
+            .fixits()
+            .iter()
+            .map(|fixit| fix(source_map, fixit))
+            .collect();
+    }
+
+    result
+}
+
+fn fix(source_map: &SourceMap, fixit: &FixIt) -> Value {
+    let span = fixit.span();
+    let uri = source_map.get(span.file_id()).map(|file| file.name());
+
+    json!({
+        "description": { "text": fixit.to_string() },
+        "artifactChanges": [{
+            "artifactLocation": { "uri": uri },
+            "replacements": [{
+                "deletedRegion": {
+                    "byteOffset": span.start(),
+                    "byteLength": span.length(),
+                },
+                "insertedContent": { "text": fixit.replacement() },
+            }],
+        }],
+    })
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Help | Severity::Note => "note",
+        Severity::Warning => "warning",
+        Severity::Error | Severity::Bug => "error",
+    }
+}
+
+fn rule_id(report: &Report) -> Option<String> {
+    match (report.code(), report.named_warning()) {
+        (Some(code), _) => Some(code.to_string()),
+        (None, Some(warning)) => Some(warning.to_string()),
+        (None, None) => None,
+    }
+}
+
+fn location(source_map: &SourceMap, annotation: &Annotation) -> Value {
+    let span = annotation.span();
+
+    let mut region = json!({
+        "byteOffset": span.start(),
+        "byteLength": span.length(),
+    });
+
+    let mut physical_location = json!({});
+    if let Some(file) = source_map.get(span.file_id()) {
+        let (start, end) = file.span_location(span);
+        region["startLine"] = json!(start.line());
+        region["startColumn"] = json!(start.utf16_column());
+        region["endLine"] = json!(end.line());
+        region["endColumn"] = json!(end.utf16_column());
+
+        physical_location["artifactLocation"] = json!({ "uri": file.name() });
+    }
+    physical_location["region"] = region;
+
+    json!({
+        "physicalLocation": physical_location,
+        "message": { "text": annotation.message() },
+    })
+}
@@ -0,0 +1,424 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{
+    annotation::Style,
+    files::SourceFiles,
+    json,
+    report::{Report, Severity},
+};
+
+use hyperc_span::source_map::SourceMap;
+
+use codespan_reporting::{
+    diagnostic::{self, LabelStyle},
+    term::{
+        self,
+        termcolor::{Buffer, WriteColor},
+        Config,
+    },
+};
+use std::{
+    cell::RefCell,
+    env,
+    fmt::Debug,
+    io::{self, IsTerminal, Write},
+    rc::Rc,
+};
+
+/// Receives every report a [`Diagnostic`](crate::Diagnostic) emits.
+pub trait DiagnosticSink: Debug {
+    fn emit(&mut self, source_map: &SourceMap, report: Report);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+
+    /// Decides whether stderr gets colors. In auto mode a non-empty `NO_COLOR`
+    /// disables them, a `CLICOLOR_FORCE` other than `0` forces them, and
+    /// otherwise they are used if stderr is a terminal.
+    pub fn use_color(&self) -> bool {
+        self.resolve(|name| env::var(name).ok(), io::stderr().is_terminal())
+    }
+
+    pub(crate) fn resolve(
+        &self,
+        variable: impl Fn(&str) -> Option<String>,
+        is_terminal: bool,
+    ) -> bool {
+        match *self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                if variable("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+                    return false;
+                }
+
+                if variable("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+                    return true;
+                }
+
+                is_terminal
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayStyle {
+    /// Shows the annotated source lines below the message.
+    #[default]
+    Rich,
+    /// Prints a single `file:line:column: severity: message` line like GCC.
+    Short,
+}
+
+/// Decides how many columns stderr has, where `0` means no limit. Output that
+/// is not a terminal has no limit, otherwise `COLUMNS` is used before the
+/// size of the terminal itself.
+pub fn message_length() -> usize {
+    let is_terminal = io::stderr().is_terminal();
+    let terminal_width = terminal_size::terminal_size_of(io::stderr())
+        .map(|(terminal_size::Width(width), _)| usize::from(width));
+    resolve_message_length(|name| env::var(name).ok(), is_terminal, terminal_width)
+}
+
+pub(crate) fn resolve_message_length(
+    variable: impl Fn(&str) -> Option<String>,
+    is_terminal: bool,
+    terminal_width: Option<usize>,
+) -> usize {
+    if !is_terminal {
+        return 0;
+    }
+
+    variable("COLUMNS")
+        .and_then(|columns| columns.parse().ok())
+        .or(terminal_width)
+        .unwrap_or(0)
+}
+
+/// Renders reports to stderr.
+#[derive(Debug, Default)]
+pub struct TerminalSink {
+    color_mode: ColorMode,
+    display_style: DisplayStyle,
+    message_length: Option<usize>,
+}
+
+impl TerminalSink {
+    pub fn new(color_mode: ColorMode) -> Self {
+        Self {
+            color_mode,
+            display_style: DisplayStyle::default(),
+            message_length: None,
+        }
+    }
+
+    pub fn with_display_style(mut self, display_style: DisplayStyle) -> Self {
+        self.display_style = display_style;
+        self
+    }
+
+    /// Fits the output into `message_length` columns instead of the detected
+    /// width of the terminal. `0` disables the limit.
+    pub fn with_message_length(mut self, message_length: usize) -> Self {
+        self.message_length = Some(message_length);
+        self
+    }
+}
+
+impl DiagnosticSink for TerminalSink {
+    fn emit(&mut self, source_map: &SourceMap, report: Report) {
+        let mut buffer = if self.color_mode.use_color() {
+            Buffer::ansi()
+        } else {
+            Buffer::no_color()
+        };
+
+        let message_length = self.message_length.unwrap_or_else(message_length);
+        render(
+            &mut buffer,
+            self.display_style,
+            message_length,
+            source_map,
+            &report,
+        );
+
+        let output = fit_lines(&String::from_utf8_lossy(buffer.as_slice()), message_length);
+        let _ = io::stderr().lock().write_all(output.as_bytes());
+    }
+}
+
+/// Writes every report to stderr as a single line of JSON.
+#[derive(Debug, Default)]
+pub struct JsonSink;
+
+impl DiagnosticSink for JsonSink {
+    fn emit(&mut self, source_map: &SourceMap, report: Report) {
+        eprintln!("{}", json::report_to_json(source_map, &report));
+    }
+}
+
+/// Collects reports in memory. Clones share the same storage, so a clone can
+/// be handed to a [`Diagnostic`](crate::Diagnostic) and inspected afterwards.
+#[derive(Clone, Debug, Default)]
+pub struct MemorySink {
+    reports: Rc<RefCell<Vec<Report>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reports(&self) -> Vec<Report> {
+        self.reports.borrow().clone()
+    }
+}
+
+impl DiagnosticSink for MemorySink {
+    fn emit(&mut self, _source_map: &SourceMap, report: Report) {
+        self.reports.borrow_mut().push(report);
+    }
+}
+
+/// Renders reports without colors into a string. Clones share the same
+/// buffer.
+#[derive(Clone, Debug, Default)]
+pub struct BufferSink {
+    buffer: Rc<RefCell<String>>,
+    display_style: DisplayStyle,
+    message_length: usize,
+}
+
+impl BufferSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_display_style(mut self, display_style: DisplayStyle) -> Self {
+        self.display_style = display_style;
+        self
+    }
+
+    /// Fits the output into `message_length` columns. `0`, the default,
+    /// disables the limit.
+    pub fn with_message_length(mut self, message_length: usize) -> Self {
+        self.message_length = message_length;
+        self
+    }
+
+    pub fn contents(&self) -> String {
+        self.buffer.borrow().clone()
+    }
+}
+
+impl DiagnosticSink for BufferSink {
+    fn emit(&mut self, source_map: &SourceMap, report: Report) {
+        let mut buffer = Buffer::no_color();
+        render(
+            &mut buffer,
+            self.display_style,
+            self.message_length,
+            source_map,
+            &report,
+        );
+
+        let output = String::from_utf8_lossy(buffer.as_slice());
+        self.buffer
+            .borrow_mut()
+            .push_str(&fit_lines(&output, self.message_length));
+    }
+}
+
+fn render(
+    writer: &mut dyn WriteColor,
+    display_style: DisplayStyle,
+    message_length: usize,
+    source_map: &SourceMap,
+    report: &Report,
+) {
+    let severity = match report.severity() {
+        Severity::Help => diagnostic::Severity::Help,
+        Severity::Note => diagnostic::Severity::Note,
+        Severity::Warning => diagnostic::Severity::Warning,
+        Severity::Error => diagnostic::Severity::Error,
+        Severity::Bug => diagnostic::Severity::Bug,
+    };
+
+    let mut message = report.message().unwrap_or_default().to_string();
+    if let Some(warning) = report.named_warning() {
+        message = format!("{} [{}]", message, warning);
+    }
+
+    // The short style puts the location before the message, so only the rich
+    // style wraps it after the `error[E0001]: ` header
+    if display_style == DisplayStyle::Rich {
+        let header =
+            report.severity().name().len() + report.code().map_or(0, |code| code.len() + 2) + 2;
+        message = wrap(&message, message_length.saturating_sub(header));
+    }
+
+    let mut diagnostic = diagnostic::Diagnostic::new(severity)
+        .with_message(message)
+        .with_labels(
+            report
+                .annotations()
+                .iter()
+                .map(|annotation| {
+                    let style = match annotation.style() {
+                        Style::Primary => LabelStyle::Primary,
+                        Style::Secondary => LabelStyle::Secondary,
+                    };
+                    diagnostic::Label::new(
+                        style,
+                        annotation.span().file_id(),
+                        annotation.span().start()..annotation.span().end(),
+                    )
+                    .with_message(annotation.message())
+                })
+                .collect(),
+        );
+
+    if let Some(code) = report.code() {
+        diagnostic = diagnostic.with_code(code);
+    }
+
+    let notes = report
+        .children()
+        .iter()
+        .map(|child| format!("{}: {}", child.kind().name(), child.message()))
+        .chain(
+            report
+                .fixits()
+                .iter()
+                .map(|fixit| format!("help: {}", fixit)),
+        )
+        .map(|note| wrap(&note, message_length.saturating_sub(4)))
+        .collect();
+    diagnostic = diagnostic.with_notes(notes);
+
+    let display_style = match display_style {
+        DisplayStyle::Rich => term::DisplayStyle::Rich,
+        DisplayStyle::Short => term::DisplayStyle::Short,
+    };
+
+    let config = Config {
+        display_style,
+        ..Default::default()
+    };
+
+    let files = SourceFiles::new(source_map, report.primary_span());
+    term::emit(writer, &config, &files, &diagnostic).unwrap();
+}
+
+/// Breaks `text` at spaces into lines of at most `width` columns. Words that
+/// are longer than a line are left whole, and a `width` of `0` disables the
+/// wrapping.
+fn wrap(text: &str, width: usize) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+
+    let mut wrapped = String::new();
+    let mut column = 0;
+    for word in text.split(' ') {
+        let length = word.chars().count();
+        if column > 0 && column + 1 + length > width {
+            wrapped.push('\n');
+            column = 0;
+        } else if column > 0 {
+            wrapped.push(' ');
+            column += 1;
+        }
+
+        wrapped.push_str(word);
+        column += length;
+    }
+
+    wrapped
+}
+
+/// Cuts every line of the rendered `output` that is wider than
+/// `message_length` columns and marks the cut with `…`. Color escape sequences
+/// are kept, so the colors of the following lines stay intact.
+fn fit_lines(output: &str, message_length: usize) -> String {
+    if message_length == 0 {
+        return output.to_string();
+    }
+
+    let mut fitted = String::with_capacity(output.len());
+    for line in output.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+
+        let width = visible_chars(line).count();
+        if width <= message_length {
+            fitted.push_str(line);
+            fitted.push_str(newline);
+            continue;
+        }
+
+        let mut column = 0;
+        let mut chars = line.chars();
+        while let Some(char) = chars.next() {
+            if char == '\x1b' {
+                fitted.push(char);
+                for char in chars.by_ref() {
+                    fitted.push(char);
+                    if char.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            column += 1;
+            if column < message_length {
+                fitted.push(char);
+            } else if column == message_length {
+                fitted.push('…');
+            }
+        }
+
+        fitted.push_str(newline);
+    }
+
+    fitted
+}
+
+fn visible_chars(line: &str) -> impl Iterator<Item = char> + '_ {
+    let mut escape = false;
+    line.chars().filter(move |&char| {
+        if char == '\x1b' {
+            escape = true;
+        } else if escape {
+            escape = !char.is_ascii_alphabetic();
+        } else {
+            return true;
+        }
+
+        false
+    })
+}
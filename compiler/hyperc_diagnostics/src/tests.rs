@@ -0,0 +1,750 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![cfg(test)]
+
+use super::*;
+use crate::{
+    annotation::Annotation,
+    errors::ErrorCode,
+    fixit::{apply_fixits, splice_fixits, Applicability, FixIt},
+    ice,
+    json::report_to_json,
+    registry::{self, EXPLANATIONS},
+    report::Severity,
+    sarif::sarif_log,
+    sink::{resolve_message_length, BufferSink, ColorMode, DisplayStyle, MemorySink},
+    warnings::{Warning, WarningGroup, WarningLevel, WarningSettings},
+};
+
+use hyperc_span::Span;
+
+use serde_json::json;
+use std::{collections::HashSet, panic};
+
+#[test]
+fn memory_sink() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", "int a = \"abc;\n");
+
+    let span = Span::new(file_id, 8, 13);
+    let report = Report::error()
+        .with_error_code(ErrorCode::E0003)
+        .with_annotations(vec![Annotation::primary(span, "missing closing quote")]);
+    diagnostic.report(report);
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].severity(), Severity::Error);
+    assert_eq!(reports[0].code(), Some("E0003"));
+    assert_eq!(reports[0].message(), Some("unclosed string literal"));
+    assert_eq!(reports[0].annotations()[0].span(), span);
+    assert_eq!(
+        reports[0].annotations()[0].message(),
+        "missing closing quote"
+    );
+}
+
+#[test]
+fn buffer_sink() {
+    let sink = BufferSink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let header_id = diagnostic.add_file("test.h", "int a;\n");
+    let source_id = diagnostic.add_file("test.c", "#include \"test.h\"\nlong a;\n");
+
+    let report = Report::error()
+        .with_message("conflicting types for `a`")
+        .with_annotations(vec![
+            Annotation::primary(Span::new(source_id, 23, 24), "redeclared here"),
+            Annotation::secondary(Span::new(header_id, 4, 5), "first declared here"),
+        ]);
+    diagnostic.report(report);
+
+    let contents = sink.contents();
+    assert!(contents.starts_with("error: conflicting types for `a`"));
+    assert!(contents.contains("test.c:2:6"));
+    assert!(contents.contains("test.h:1:5"));
+    assert!(contents.contains("redeclared here"));
+    assert!(contents.contains("first declared here"));
+    assert!(!contents.contains('\u{1b}'));
+}
+
+#[test]
+fn json_report() {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add("test.c", "int a;\nchar *s = \"ö;\n");
+
+    let report = Report::error()
+        .with_error_code(ErrorCode::E0003)
+        .with_annotations(vec![Annotation::primary(
+            Span::new(file_id, 17, 21),
+            "missing closing quote",
+        )]);
+
+    assert_eq!(
+        report_to_json(&source_map, &report),
+        json!({
+            "severity": "error",
+            "code": "E0003",
+            "message": "unclosed string literal",
+            "warning": null,
+            "annotations": [{
+                "file_name": "test.c",
+                "byte_start": 17,
+                "byte_end": 21,
+                "line_start": 2,
+                "line_end": 2,
+                "column_start": 11,
+                "column_end": 15,
+                "is_primary": true,
+                "message": "missing closing quote",
+            }],
+            "children": [],
+            "fixits": [],
+        })
+    );
+    assert!(!report_to_json(&source_map, &report)
+        .to_string()
+        .contains('\n'));
+}
+
+#[test]
+fn sarif_report() {
+    let mut source_map = SourceMap::new();
+    let header_id = source_map.add("test.h", "int a;\n");
+    let source_id = source_map.add("test.c", "long ö = 0x;\n");
+
+    let reports = [
+        Report::error()
+            .with_error_code(ErrorCode::E0002)
+            .with_annotations(vec![
+                Annotation::primary(Span::new(source_id, 10, 12), "expected digits"),
+                Annotation::secondary(Span::new(header_id, 4, 5), "declared here"),
+            ]),
+        Report::warning().with_message("multi-character character constant"),
+    ];
+
+    let log = sarif_log("hyperc", "0.1.0", &source_map, &reports);
+    assert_eq!(log["version"], "2.1.0");
+
+    let run = &log["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "hyperc");
+    assert_eq!(run["tool"]["driver"]["rules"], json!([{ "id": "E0002" }]));
+
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "E0002");
+    assert_eq!(result["level"], "error");
+    assert_eq!(result["message"]["text"], "unclosed hexadecimal number");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"],
+        json!({
+            "artifactLocation": { "uri": "test.c" },
+            "region": {
+                "byteOffset": 10,
+                "byteLength": 2,
+                "startLine": 1,
+                "startColumn": 10,
+                "endLine": 1,
+                "endColumn": 12,
+            },
+        })
+    );
+    assert_eq!(
+        result["relatedLocations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+        "test.h"
+    );
+
+    let result = &run["results"][1];
+    assert!(result.get("ruleId").is_none());
+    assert_eq!(result["level"], "warning");
+}
+
+#[test]
+fn short_display_style() {
+    let sink = BufferSink::new().with_display_style(DisplayStyle::Short);
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", "int a;\nchar *s = \"abc;\n");
+
+    let report = Report::error()
+        .with_error_code(ErrorCode::E0003)
+        .with_annotations(vec![Annotation::primary(
+            Span::new(file_id, 17, 21),
+            "missing closing quote",
+        )]);
+    diagnostic.report(report);
+
+    assert_eq!(
+        sink.contents(),
+        "test.c:2:11: error[E0003]: unclosed string literal\n"
+    );
+}
+
+#[test]
+fn color_mode() {
+    let variables = |no_color: Option<&'static str>, clicolor_force: Option<&'static str>| {
+        move |name: &str| match name {
+            "NO_COLOR" => no_color.map(String::from),
+            "CLICOLOR_FORCE" => clicolor_force.map(String::from),
+            _ => None,
+        }
+    };
+
+    assert!(ColorMode::Always.resolve(variables(Some("1"), None), false));
+    assert!(!ColorMode::Never.resolve(variables(None, Some("1")), true));
+
+    assert!(ColorMode::Auto.resolve(variables(None, None), true));
+    assert!(!ColorMode::Auto.resolve(variables(None, None), false));
+    assert!(!ColorMode::Auto.resolve(variables(Some("1"), None), true));
+    assert!(ColorMode::Auto.resolve(variables(Some(""), None), true));
+    assert!(ColorMode::Auto.resolve(variables(None, Some("1")), false));
+    assert!(!ColorMode::Auto.resolve(variables(None, Some("0")), false));
+    assert!(!ColorMode::Auto.resolve(variables(Some("1"), Some("1")), true));
+
+    assert_eq!(ColorMode::from_name("never"), Some(ColorMode::Never));
+    assert_eq!(ColorMode::from_name("sometimes"), None);
+}
+
+#[test]
+fn message_length_detection() {
+    let columns = |value: Option<&'static str>| move |_: &str| value.map(String::from);
+
+    assert_eq!(
+        resolve_message_length(columns(Some("100")), false, Some(80)),
+        0
+    );
+    assert_eq!(
+        resolve_message_length(columns(Some("100")), true, Some(80)),
+        100
+    );
+    assert_eq!(
+        resolve_message_length(columns(Some("wide")), true, Some(80)),
+        80
+    );
+    assert_eq!(resolve_message_length(columns(None), true, Some(80)), 80);
+    assert_eq!(resolve_message_length(columns(None), true, None), 0);
+}
+
+#[test]
+fn message_length_fitting() {
+    let sink = BufferSink::new().with_message_length(30);
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let source = "int value = first_long_identifier + second_long_identifier;\n";
+    let file_id = diagnostic.add_file("test.c", source);
+
+    let report = Report::error()
+        .with_message("use of undeclared identifier `second_long_identifier`")
+        .with_annotations(vec![Annotation::primary(
+            Span::new(file_id, 36, 58),
+            "not found in this scope",
+        )])
+        .with_note("identifiers must be declared before they are used");
+    diagnostic.report(report);
+
+    let contents = sink.contents();
+    assert!(contents.lines().all(|line| line.chars().count() <= 30));
+    assert!(contents.starts_with("error: use of undeclared\nidentifier\n"));
+    assert!(contents.contains("1 │ int value = first_long_id…\n"));
+    assert!(contents.contains("= note: identifiers must be\n"));
+
+    // Without a limit nothing is cut
+    let sink = BufferSink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", source);
+    diagnostic.report(
+        Report::error().with_annotations(vec![Annotation::primary(Span::new(file_id, 36, 58), "")]),
+    );
+    assert!(sink.contents().contains(source.trim_end()));
+}
+
+#[test]
+fn severity_counts() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    assert_eq!(diagnostic.summary(), None);
+
+    diagnostic.report(Report::warning().with_message("first warning"));
+    assert!(!diagnostic.has_errors());
+    assert_eq!(diagnostic.summary().unwrap(), "1 warning generated");
+
+    diagnostic.report(Report::error().with_message("first error"));
+    diagnostic.report(Report::error().with_message("second error"));
+    diagnostic.report(Report::warning().with_message("second warning"));
+    diagnostic.report(Report::note().with_message("note"));
+
+    assert!(diagnostic.has_errors());
+    assert_eq!(diagnostic.error_count(), 2);
+    assert_eq!(diagnostic.warning_count(), 2);
+    assert_eq!(diagnostic.count(Severity::Note), 1);
+    assert_eq!(
+        diagnostic.summary().unwrap(),
+        "2 errors, 2 warnings generated"
+    );
+    assert_eq!(sink.reports().len(), 5);
+}
+
+#[test]
+fn warnings_as_errors() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_warnings_as_errors(true);
+
+    diagnostic.report(Report::warning().with_message("warning"));
+    diagnostic.report(Report::note().with_message("note"));
+
+    assert_eq!(diagnostic.error_count(), 1);
+    assert_eq!(diagnostic.warning_count(), 0);
+    assert_eq!(sink.reports()[0].severity(), Severity::Error);
+    assert_eq!(sink.reports()[1].severity(), Severity::Note);
+}
+
+#[test]
+fn error_limits() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone()).with_max_errors(2);
+
+    diagnostic.report(Report::error().with_message("first error"));
+    diagnostic.report(Report::warning().with_message("warning"));
+    assert!(!diagnostic.has_reached_error_limit());
+    diagnostic.report(Report::error().with_message("second error"));
+    assert!(diagnostic.has_reached_error_limit());
+    diagnostic.report(Report::error().with_message("third error"));
+
+    let messages = sink
+        .reports()
+        .iter()
+        .map(|report| report.message().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "first error",
+            "warning",
+            "second error",
+            "compilation terminated due to -fmax-errors=2"
+        ]
+    );
+    assert_eq!(diagnostic.error_count(), 2);
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_max_errors(5)
+        .with_fatal_errors(true);
+
+    diagnostic.report(Report::error().with_message("first error"));
+    diagnostic.report(Report::error().with_message("second error"));
+    assert_eq!(diagnostic.error_count(), 1);
+    assert_eq!(
+        sink.reports()[1].message(),
+        Some("compilation terminated due to -Wfatal-errors")
+    );
+}
+
+#[test]
+fn warning_names() {
+    for warning in Warning::ALL {
+        assert_eq!(Warning::from_name(warning.name()), Some(*warning));
+    }
+
+    assert_eq!(Warning::Multichar.to_string(), "-Wmultichar");
+    assert_eq!(WarningGroup::from_name("all"), Some(WarningGroup::All));
+    assert_eq!(Warning::from_name("all"), None);
+}
+
+#[test]
+fn warning_settings() {
+    let mut settings = WarningSettings::new();
+    assert_eq!(settings.level(Warning::Multichar), WarningLevel::Warning);
+    assert_eq!(
+        settings.level(Warning::UnusedVariable),
+        WarningLevel::Ignored
+    );
+
+    assert!(settings.apply_option("error=unused-variable"));
+    assert!(settings.apply_option("all"));
+    assert!(settings.apply_option("no-multichar"));
+    assert!(!settings.apply_option("no-such-warning"));
+
+    assert_eq!(settings.level(Warning::UnusedVariable), WarningLevel::Error);
+    assert_eq!(
+        settings.level(Warning::UnknownPragmas),
+        WarningLevel::Warning
+    );
+    assert_eq!(settings.level(Warning::Multichar), WarningLevel::Ignored);
+    assert_eq!(
+        settings.level(Warning::ImplicitIntConversion),
+        WarningLevel::Ignored
+    );
+
+    assert!(settings.apply_option("extra"));
+    assert_eq!(
+        settings.level(Warning::ImplicitIntConversion),
+        WarningLevel::Warning
+    );
+}
+
+#[test]
+fn warning_filtering() {
+    let sink = MemorySink::new();
+    let mut settings = WarningSettings::new();
+    settings.apply_option("error=unused-variable");
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_warning_settings(settings);
+
+    let warning = |warning: Warning| {
+        Report::warning()
+            .with_message(warning.name())
+            .with_warning(warning)
+    };
+
+    diagnostic.report(warning(Warning::Multichar));
+    diagnostic.report(warning(Warning::ImplicitIntConversion));
+    diagnostic.report(warning(Warning::UnusedVariable));
+
+    assert!(diagnostic.apply_pragma("push"));
+    assert!(diagnostic.apply_pragma("ignored \"-Wmultichar\""));
+    assert!(diagnostic.apply_pragma("warning \"-Wimplicit-int-conversion\""));
+    diagnostic.report(warning(Warning::Multichar));
+    diagnostic.report(warning(Warning::ImplicitIntConversion));
+    assert!(diagnostic.apply_pragma("pop"));
+    assert!(!diagnostic.apply_pragma("pop"));
+    diagnostic.report(warning(Warning::Multichar));
+
+    assert!(!diagnostic.apply_pragma("ignored -Wmultichar"));
+    assert!(!diagnostic.apply_pragma("ignored \"-Wno-such-warning\""));
+    assert!(!diagnostic.apply_pragma("fatal \"-Wmultichar\""));
+
+    let reports = sink
+        .reports()
+        .iter()
+        .map(|report| (report.severity(), report.message().unwrap().to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reports,
+        [
+            (Severity::Warning, String::from("multichar")),
+            (Severity::Error, String::from("unused-variable")),
+            (Severity::Warning, String::from("implicit-int-conversion")),
+            (Severity::Warning, String::from("multichar")),
+        ]
+    );
+    assert_eq!(diagnostic.error_count(), 1);
+    assert_eq!(diagnostic.warning_count(), 3);
+}
+
+#[test]
+fn warning_name_display() {
+    let sink = BufferSink::new().with_display_style(DisplayStyle::Short);
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", "int c = 'ab';\n");
+
+    let report = Report::warning()
+        .with_message("multi-character character constant")
+        .with_warning(Warning::Multichar)
+        .with_annotations(vec![Annotation::primary(Span::new(file_id, 8, 12), "")]);
+    diagnostic.report(report);
+
+    assert_eq!(
+        sink.contents(),
+        "test.c:1:9: warning: multi-character character constant [-Wmultichar]\n"
+    );
+}
+
+#[test]
+fn children_and_fixits() {
+    let sink = BufferSink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let source = "char *s = \"abc;\n";
+    let file_id = diagnostic.add_file("test.c", source);
+
+    let span = Span::new(file_id, 10, 15);
+    let fixit = FixIt::insertion(span.shrink_to_hi(), "\"", Applicability::MachineApplicable);
+    let report = Report::error()
+        .with_error_code(ErrorCode::E0003)
+        .with_annotations(vec![Annotation::primary(span, "unclosed string")])
+        .with_note("string literals cannot span multiple lines")
+        .with_help("use a line splice to continue the string")
+        .with_fixit(fixit.clone());
+    diagnostic.report(report.clone());
+
+    let contents = sink.contents();
+    assert!(contents.contains("= note: string literals cannot span multiple lines"));
+    assert!(contents.contains("= help: use a line splice to continue the string"));
+    assert!(contents.contains("= help: insert `\"`"));
+
+    assert_eq!(diagnostic.fixits(), std::slice::from_ref(&fixit));
+    assert_eq!(apply_fixits(source, &[fixit]), "char *s = \"abc;\"\n");
+
+    let value = report_to_json(&diagnostic.source_map(), &report);
+    assert_eq!(value["children"][1]["kind"], "help");
+    assert_eq!(value["fixits"][0]["replacement"], "\"");
+    assert_eq!(value["fixits"][0]["applicability"], "machine-applicable");
+    assert_eq!(value["fixits"][0]["column_start"], 16);
+
+    let log = sarif_log("hyperc", "0.1.0", &diagnostic.source_map(), &[report]);
+    let fix = &log["runs"][0]["results"][0]["fixes"][0];
+    assert_eq!(fix["description"]["text"], "insert `\"`");
+    assert_eq!(
+        fix["artifactChanges"][0]["replacements"][0]["deletedRegion"]["byteOffset"],
+        15
+    );
+}
+
+#[test]
+fn fixit_application() {
+    let file_id = hyperc_span::FileId::default();
+    let source = "int main() { return 0 }";
+
+    let fixits = [
+        FixIt::new(
+            Span::new(file_id, 0, 3),
+            "long",
+            Applicability::MachineApplicable,
+        ),
+        FixIt::insertion(
+            Span::new(file_id, 21, 22),
+            ";",
+            Applicability::MachineApplicable,
+        ),
+        FixIt::removal(Span::new(file_id, 1, 2), Applicability::MachineApplicable),
+        FixIt::removal(Span::new(file_id, 12, 13), Applicability::MaybeIncorrect),
+    ];
+
+    assert_eq!(apply_fixits(source, &fixits), "long main() {return 0; }");
+    assert_eq!(fixits[0].to_string(), "replace with `long`");
+    assert_eq!(fixits[2].to_string(), "remove this code");
+}
+
+#[test]
+fn fixit_splicing() {
+    let file_id = hyperc_span::FileId::default();
+    let source = b"int x = 1 /* \xff */\n";
+    let fixit = FixIt::insertion(
+        Span::new(file_id, 9, 9),
+        ";",
+        Applicability::MachineApplicable,
+    );
+
+    assert_eq!(
+        splice_fixits(source, &[fixit]),
+        b"int x = 1; /* \xff */\n".to_vec()
+    );
+}
+
+#[test]
+fn error_code_explanations() {
+    let mut codes = HashSet::new();
+    for error_code in ErrorCode::ALL {
+        let code = error_code.code();
+        assert!(codes.insert(code), "{code} is used by more than one error");
+        assert!(
+            registry::explanation(code).is_some(),
+            "{code} has no explanation"
+        );
+        assert!(!error_code.explanation().is_empty());
+    }
+
+    let mut explained = HashSet::new();
+    for (code, explanation) in EXPLANATIONS {
+        assert!(explained.insert(*code), "{code} is explained twice");
+        assert!(codes.contains(code), "{code} is not an error code");
+        assert!(explanation.contains("```c") || explanation.contains("```text"));
+    }
+
+    assert_eq!(registry::explanation("E9999"), None);
+}
+
+#[test]
+fn help_and_bug_severities() {
+    let sink = BufferSink::new().with_display_style(DisplayStyle::Short);
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+
+    diagnostic.report(Report::help().with_message("try this"));
+    assert!(!diagnostic.has_errors());
+    diagnostic.report(Report::bug().with_message("something broke"));
+    assert!(diagnostic.has_errors());
+    assert_eq!(diagnostic.count(Severity::Bug), 1);
+
+    assert_eq!(sink.contents(), "help: try this\nbug: something broke\n");
+}
+
+#[test]
+fn internal_compiler_error() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone()).with_max_errors(1);
+    let file_id = diagnostic.add_file("test.c", "int a;\n");
+
+    // The panic hook is process-wide, so it is left alone and the location of
+    // the panic stays unknown
+    let payload = panic::catch_unwind(|| {
+        let _stage = ice::enter_stage("parsing");
+        ice::set_current_span(Span::new(file_id, 4, 5));
+        panic!("unexpected token");
+    })
+    .unwrap_err();
+
+    let ice = ice::Ice::from_panic(payload.as_ref());
+    assert_eq!(ice.message(), "unexpected token");
+    assert_eq!(ice.stage(), Some("parsing"));
+    assert_eq!(ice.span(), Some(Span::new(file_id, 4, 5)));
+    assert_eq!(ice.location(), None);
+
+    let other = ice::Ice::from_panic(&String::from("again"));
+    assert_eq!(other.message(), "again");
+    assert_eq!(other.stage(), None);
+
+    // The crash is reported past the error limit and inside a speculative stage
+    diagnostic.report(Report::error().with_message("first"));
+    let _checkpoint = diagnostic.checkpoint();
+    diagnostic.report(Report::error().with_message("speculative"));
+    diagnostic.report_ice(ice.to_report("1.2.3"));
+    diagnostic.report(Report::error().with_message("dropped"));
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 3);
+    let report = &reports[2];
+    assert_eq!(report.severity(), Severity::Bug);
+    assert_eq!(
+        report.message(),
+        Some("internal compiler error: unexpected token")
+    );
+    assert_eq!(report.annotations()[0].span(), Span::new(file_id, 4, 5));
+
+    let children = report
+        .children()
+        .iter()
+        .map(|child| child.message())
+        .collect::<Vec<_>>();
+    assert_eq!(children[0], "hyperc 1.2.3 unexpectedly panicked");
+    assert_eq!(children[1], "the crash happened while parsing");
+    assert!(children[2].contains("reduced reproducer"));
+}
+
+#[test]
+fn catch_internal_compiler_error() {
+    let ice = ice::catch_ice(|| {
+        let _stage = ice::enter_stage("lowering");
+        panic!("missing type");
+    })
+    .unwrap_err();
+    assert_eq!(ice.message(), "missing type");
+    assert_eq!(ice.stage(), Some("lowering"));
+    assert!(ice.location().unwrap().contains("tests.rs"));
+
+    // Nested calls share the hook that was installed by the first one
+    let result = ice::catch_ice(|| ice::catch_ice(|| panic!("inner")).unwrap_err());
+    assert_eq!(result.unwrap().message(), "inner");
+    assert_eq!(ice::catch_ice(|| 1).unwrap(), 1);
+}
+
+fn error_at(error_code: ErrorCode, span: Span) -> Report {
+    Report::error()
+        .with_error_code(error_code)
+        .with_annotations(vec![Annotation::primary(span, "")])
+}
+
+fn codes(sink: &MemorySink) -> Vec<String> {
+    sink.reports()
+        .iter()
+        .map(|report| report.code().unwrap_or("-").to_string())
+        .collect()
+}
+
+#[test]
+fn buffered_reports() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_buffering(true);
+    let header_id = diagnostic.add_file("test.h", "int a;\n");
+    let source_id = diagnostic.add_file("test.c", "int b = 0x;\n");
+
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(source_id, 8, 10)));
+    diagnostic.report(error_at(ErrorCode::E0003, Span::new(source_id, 4, 5)));
+    diagnostic.report(Report::warning().with_message("without span"));
+    diagnostic.report(error_at(
+        ErrorCode::E0001(String::from("@")),
+        Span::new(header_id, 4, 5),
+    ));
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(source_id, 8, 10)));
+    diagnostic.report(error_at(ErrorCode::E0004, Span::new(source_id, 8, 10)));
+    diagnostic.report(Report::warning().with_message("without span"));
+
+    assert!(sink.reports().is_empty());
+    assert_eq!(diagnostic.error_count(), 4);
+    assert_eq!(diagnostic.warning_count(), 1);
+
+    diagnostic.flush();
+    assert_eq!(codes(&sink), ["E0001", "E0003", "E0002", "E0004", "-"]);
+
+    diagnostic.flush();
+    assert_eq!(sink.reports().len(), 5);
+}
+
+#[test]
+fn speculative_reports() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", "int a = 0x;\n");
+
+    let checkpoint = diagnostic.checkpoint();
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(file_id, 8, 10)));
+    assert!(sink.reports().is_empty());
+    assert_eq!(diagnostic.error_count(), 0);
+    diagnostic.discard(checkpoint);
+
+    assert!(sink.reports().is_empty());
+    assert_eq!(diagnostic.error_count(), 0);
+
+    let outer = diagnostic.checkpoint();
+    diagnostic.report(error_at(ErrorCode::E0003, Span::new(file_id, 4, 5)));
+    let inner = diagnostic.checkpoint();
+    diagnostic.report(error_at(ErrorCode::E0005, Span::new(file_id, 0, 3)));
+    diagnostic.discard(inner);
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(file_id, 8, 10)));
+    assert!(sink.reports().is_empty());
+    diagnostic.commit(outer);
+
+    assert_eq!(codes(&sink), ["E0003", "E0002"]);
+    assert_eq!(diagnostic.error_count(), 2);
+
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(file_id, 8, 10)));
+    assert_eq!(sink.reports().len(), 3);
+}
+
+#[test]
+fn speculative_reports_across_flushes() {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_buffering(true)
+        .with_max_errors(2);
+    let file_id = diagnostic.add_file("test.c", "int a = 0x;\n");
+
+    diagnostic.report(error_at(ErrorCode::E0003, Span::new(file_id, 4, 5)));
+    let checkpoint = diagnostic.checkpoint();
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(file_id, 8, 10)));
+    diagnostic.report(error_at(ErrorCode::E0005, Span::new(file_id, 0, 3)));
+    assert!(!diagnostic.has_reached_error_limit());
+
+    diagnostic.flush();
+    assert_eq!(codes(&sink), ["E0003"]);
+    diagnostic.discard(checkpoint);
+
+    let checkpoint = diagnostic.checkpoint();
+    diagnostic.report(error_at(ErrorCode::E0002, Span::new(file_id, 8, 10)));
+    diagnostic.report(error_at(ErrorCode::E0005, Span::new(file_id, 0, 3)));
+    diagnostic.flush();
+    diagnostic.commit(checkpoint);
+    diagnostic.flush();
+
+    assert_eq!(codes(&sink), ["E0003", "E0002", "-"]);
+    assert_eq!(diagnostic.error_count(), 2);
+    assert!(diagnostic.has_reached_error_limit());
+}
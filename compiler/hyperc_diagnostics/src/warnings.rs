@@ -0,0 +1,166 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Warning {
+    C23Extensions,
+    Cpp,
+    ImplicitIntConversion,
+    MacroRedefined,
+    Multichar,
+    NullCharacter,
+    UnknownPragmas,
+    UnusedVariable,
+}
+
+impl Warning {
+    pub const ALL: &'static [Warning] = &[
+        Warning::C23Extensions,
+        Warning::Cpp,
+        Warning::ImplicitIntConversion,
+        Warning::MacroRedefined,
+        Warning::Multichar,
+        Warning::NullCharacter,
+        Warning::UnknownPragmas,
+        Warning::UnusedVariable,
+    ];
+
+    /// Returns the name used in `-W<name>` options.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Warning::C23Extensions => "c23-extensions",
+            Warning::Cpp => "cpp",
+            Warning::ImplicitIntConversion => "implicit-int-conversion",
+            Warning::MacroRedefined => "macro-redefined",
+            Warning::Multichar => "multichar",
+            Warning::NullCharacter => "null-character",
+            Warning::UnknownPragmas => "unknown-pragmas",
+            Warning::UnusedVariable => "unused-variable",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|warning| warning.name() == name)
+    }
+
+    pub fn is_enabled_by_default(&self) -> bool {
+        match *self {
+            Warning::C23Extensions
+            | Warning::Cpp
+            | Warning::MacroRedefined
+            | Warning::Multichar
+            | Warning::NullCharacter => true,
+            Warning::ImplicitIntConversion | Warning::UnknownPragmas | Warning::UnusedVariable => {
+                false
+            }
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "-W{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningGroup {
+    All,
+    Extra,
+}
+
+impl WarningGroup {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            WarningGroup::All => "all",
+            WarningGroup::Extra => "extra",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(WarningGroup::All),
+            "extra" => Some(WarningGroup::Extra),
+            _ => None,
+        }
+    }
+
+    pub fn warnings(&self) -> &'static [Warning] {
+        match *self {
+            WarningGroup::All => &[Warning::UnknownPragmas, Warning::UnusedVariable],
+            WarningGroup::Extra => &[Warning::ImplicitIntConversion],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningLevel {
+    Ignored,
+    Warning,
+    Error,
+}
+
+/// The level of every named warning, changed by `-W` options and pragmas.
+#[derive(Clone, Debug, Default)]
+pub struct WarningSettings {
+    levels: HashMap<Warning, WarningLevel>,
+}
+
+impl WarningSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn level(&self, warning: Warning) -> WarningLevel {
+        match self.levels.get(&warning) {
+            Some(level) => *level,
+            None if warning.is_enabled_by_default() => WarningLevel::Warning,
+            None => WarningLevel::Ignored,
+        }
+    }
+
+    pub fn set_level(&mut self, warning: Warning, level: WarningLevel) {
+        self.levels.insert(warning, level);
+    }
+
+    /// Applies a `-W` option without its prefix, such as `all`,
+    /// `unused-variable`, `no-multichar` or `error=multichar`. Returns `false`
+    /// if the option names no known warning or group.
+    pub fn apply_option(&mut self, option: &str) -> bool {
+        let (name, level) = if let Some(name) = option.strip_prefix("error=") {
+            (name, WarningLevel::Error)
+        } else if let Some(name) = option.strip_prefix("no-") {
+            (name, WarningLevel::Ignored)
+        } else {
+            (option, WarningLevel::Warning)
+        };
+
+        let warnings = match (Warning::from_name(name), WarningGroup::from_name(name)) {
+            (Some(warning), _) => vec![warning],
+            (None, Some(group)) => group.warnings().to_vec(),
+            (None, None) => return false,
+        };
+
+        for warning in warnings {
+            // Enabling a group must not downgrade warnings that are errors
+            if level == WarningLevel::Warning && self.level(warning) == WarningLevel::Error {
+                continue;
+            }
+
+            self.set_level(warning, level);
+        }
+
+        true
+    }
+}
@@ -4,62 +4,249 @@
  * SPDX-License-Identifier: MIT
  */
 
+pub mod standard;
 mod tests;
 pub mod token;
 
-use crate::token::{BinaryOperation, Delimiter, Keyword, LiteralKind, Token, TokenKind};
+use crate::{
+    standard::Standard,
+    token::{
+        BinaryOperation, DataModel, Delimiter, Encoding, IntegerConstant, IntegerSuffix, Keyword,
+        LiteralKind, LiteralValue, Radix, Token, TokenKind, Trivia,
+    },
+};
 
-use hyperc_diagnostics::{annotation::Annotation, errors::ErrorCode, report::Report, Diagnostic};
-use hyperc_span::Span;
+use hyperc_diagnostics::{
+    annotation::Annotation,
+    errors::ErrorCode,
+    fixit::{Applicability, FixIt},
+    ice,
+    report::Report,
+    warnings::Warning,
+    Diagnostic,
+};
+use hyperc_span::{symbol::Symbol, FileId, Span};
+use unicode_xid::UnicodeXID;
+
+/// Returned by [`Lexer::lex`] when at least one lexical error was reported.
+///
+/// The lexer recovers from every error by emitting a [`TokenKind::Error`] token,
+/// so the complete token stream is still available.
+#[derive(Debug)]
+pub struct LexError {
+    tokens: Vec<Token>,
+    error_count: usize,
+}
+
+impl LexError {
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.tokens
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.error_count
+    }
+}
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
     diagnostic: &'a Diagnostic,
+    file_id: FileId,
     text: &'a str,
+    standard: Standard,
+    trivia: bool,
+    header_name: bool,
+    pp_numbers: bool,
 
     current_character: char,
+    /// Whether `current_character` is past the end of the text, rather than a
+    /// null character in it.
+    past_end: bool,
     index: usize,
-
-    line: u64,
-    column: u64,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(diagnostic: &'a Diagnostic, text: &'a str) -> Self {
+    pub fn new(diagnostic: &'a Diagnostic, file_id: FileId, text: &'a str) -> Self {
         Self {
             diagnostic,
+            file_id,
             text,
+            standard: Standard::default(),
+            trivia: false,
+            header_name: false,
+            pp_numbers: false,
 
             current_character: '\0',
+            past_end: false,
             index: 0,
+        }
+    }
+
+    pub fn with_standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    /// Makes the lexer emit whitespace, newlines, comments and line splices as
+    /// [`TokenKind::Trivia`], so that the token spans cover the whole input.
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    /// Makes the lexer read `<...>` and `"..."` as [`TokenKind::HeaderName`]
+    /// if they are closed on the same line, as done after `#include`.
+    pub fn with_header_name(mut self, header_name: bool) -> Self {
+        self.header_name = header_name;
+        self
+    }
+
+    /// Makes the lexer read a whole preprocessing number (C11 6.4.8), such as
+    /// `1e` or `0x1e+x`, as one [`LiteralKind::Number`], as done by the
+    /// preprocessor. A number that is no valid constant has no value.
+    pub fn with_pp_numbers(mut self, pp_numbers: bool) -> Self {
+        self.pp_numbers = pp_numbers;
+        self
+    }
+
+    /// Starts lexing at byte `offset` of the text, which has to be the start of
+    /// a token.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.index = offset;
+        self
+    }
+
+    pub fn from_bytes(
+        diagnostic: &'a Diagnostic,
+        file_id: FileId,
+        bytes: &'a [u8],
+    ) -> Option<Self> {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Some(Self::new(diagnostic, file_id, text)),
+            Err(error) => {
+                let start_index = error.valid_up_to();
+                let end_index =
+                    start_index + error.error_len().unwrap_or(bytes.len() - start_index);
+                let span = Span::new(file_id, start_index, end_index);
+
+                let error = "invalid byte sequence";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0013)
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                diagnostic.report(report);
 
-            line: 1,
-            column: 0,
+                None
+            }
         }
     }
 
-    pub fn lex(&mut self) -> Option<Vec<Token>> {
+    pub fn lex(&mut self) -> Result<Vec<Token>, LexError> {
+        let _stage = ice::enter_stage("lexing");
         let mut tokens = Vec::new();
 
-        while !self.has_reached_end() {
-            let token = self.next_token()?;
+        let mut end_index = self.index;
+        while !self.has_reached_end() && !self.diagnostic.has_reached_error_limit() {
+            let token = self.next_token();
             if token.kind() == TokenKind::Eof {
                 break;
             }
 
+            // Line splices in front of a token are skipped before its span starts
+            if self.trivia && token.span().start() > end_index {
+                let span = Span::new(self.file_id, end_index, token.span().start());
+                tokens.push(Token::new(TokenKind::Trivia(Trivia::LineSplice), span));
+            }
+
+            end_index = token.span().end();
             tokens.push(token);
         }
 
-        Some(tokens)
+        if self.trivia && self.has_reached_end() && end_index < self.text.len() {
+            let span = Span::new(self.file_id, end_index, self.text.len());
+            tokens.push(Token::new(TokenKind::Trivia(Trivia::LineSplice), span));
+        }
+
+        let error_count = tokens
+            .iter()
+            .filter(|token| token.kind() == TokenKind::Error)
+            .count();
+        if error_count > 0 {
+            return Err(LexError {
+                tokens,
+                error_count,
+            });
+        }
+
+        Ok(tokens)
+    }
+
+    /// The byte offset the next token starts at.
+    pub fn offset(&self) -> usize {
+        self.index
     }
 
-    fn next_token(&mut self) -> Option<Token> {
+    fn next_token(&mut self) -> Token {
+        ice::set_current_span(Span::new(self.file_id, self.index, self.index));
+
+        let token = self.lex_token();
+        match token.kind() {
+            TokenKind::Identifier(_) => {
+                let name = self.identifier_name(token.span());
+                token.with_symbol(Symbol::intern(&name))
+            }
+            TokenKind::Literal(_) => {
+                let spelling = &self.text[token.span().start()..token.span().end()];
+                let spelling = spelling.replace("\\\r\n", "").replace("\\\n", "");
+                token.with_symbol(Symbol::intern(&spelling))
+            }
+            _ => token,
+        }
+    }
+
+    fn lex_token(&mut self) -> Token {
+        // Comments are skipped in a loop, so that any number of them can
+        // follow each other
+        loop {
+            let token = self.lex_token_or_comment();
+            if self.trivia || !matches!(token.kind(), TokenKind::Trivia(_)) {
+                return token;
+            }
+        }
+    }
+
+    fn lex_token_or_comment(&mut self) -> Token {
         self.advance();
-        self.skip_whitespace();
+        if !self.trivia {
+            self.skip_whitespace();
+        }
 
-        let start_index = self.index - 1;
+        let start_index = self.index - self.current_character.len_utf8();
+
+        if self.header_name && matches!(self.current_character, '<' | '"') && self.lex_header_name()
+        {
+            let span = Span::new(self.file_id, start_index, self.index);
+            return Token::new(TokenKind::HeaderName, span);
+        }
 
         let kind = match self.current_character {
+            '\r' if self.peek(0) == '\n' => {
+                self.advance();
+                TokenKind::Trivia(Trivia::Newline)
+            }
+            '\n' => TokenKind::Trivia(Trivia::Newline),
+            ' ' | '\t' | '\r' => {
+                while matches!(self.peek(0), ' ' | '\t')
+                    || (self.peek(0) == '\r' && self.peek(1) != '\n')
+                {
+                    self.advance();
+                }
+
+                TokenKind::Trivia(Trivia::Whitespace)
+            }
             '[' => TokenKind::OpenDelimiter(Delimiter::Bracket),
             ']' => TokenKind::CloseDelimiter(Delimiter::Bracket),
             '(' => TokenKind::OpenDelimiter(Delimiter::Parenthesis),
@@ -67,7 +254,11 @@ impl<'a> Lexer<'a> {
             '{' => TokenKind::OpenDelimiter(Delimiter::Brace),
             '}' => TokenKind::CloseDelimiter(Delimiter::Brace),
             '.' => {
-                if self.peek(0) == '.' && self.peek(1) == '.' {
+                if self.peek(0).is_ascii_digit() {
+                    let kind = self.lex_floating_constant(start_index, Radix::Decimal, true);
+                    let token = Token::new(kind, Span::new(self.file_id, start_index, self.index));
+                    return self.lex_pp_number_rest(token);
+                } else if self.peek(0) == '.' && self.peek(1) == '.' {
                     self.advance();
                     self.advance();
                     TokenKind::Ellipsis
@@ -132,11 +323,40 @@ impl<'a> Lexer<'a> {
             '/' => match self.peek(0) {
                 '/' => {
                     self.advance();
-                    while self.peek(0) != '\n' {
+                    while self.peek(0) != '\n' && !self.has_reached_end() {
                         self.advance();
                     }
 
-                    return self.next_token();
+                    TokenKind::Trivia(Trivia::LineComment)
+                }
+                '*' => {
+                    self.advance();
+
+                    let opening_span = Span::new(self.file_id, start_index, self.index);
+                    loop {
+                        if self.has_reached_end() {
+                            let error = "comment starts here";
+                            let end_span = Span::new(self.file_id, self.index, self.index);
+                            let fixit =
+                                FixIt::insertion(end_span, "*/", Applicability::MachineApplicable);
+                            let report = Report::error()
+                                .with_error_code(ErrorCode::E0012)
+                                .with_annotations(vec![Annotation::primary(opening_span, error)])
+                                .with_fixit(fixit);
+                            self.diagnostic.report(report);
+
+                            let span = Span::new(self.file_id, start_index, self.index);
+                            return Token::new(TokenKind::Error, span);
+                        }
+
+                        self.advance();
+                        if self.current_character == '*' && self.peek(0) == '/' {
+                            self.advance();
+                            break;
+                        }
+                    }
+
+                    TokenKind::Trivia(Trivia::BlockComment)
                 }
                 '=' => {
                     self.advance();
@@ -225,69 +445,384 @@ impl<'a> Lexer<'a> {
                     TokenKind::PoundSign
                 }
             }
-            '"' => self.lex_string_literal()?,
-            '_' | 'a'..='z' | 'A'..='Z' => self.lex_identifier_or_keyword(),
-            '0'..='9' => self.lex_number_constant()?,
-            '\0' => TokenKind::Eof,
+            '"' => return self.lex_string_literal(start_index, Encoding::None),
+            '\'' => return self.lex_character_constant(start_index, Encoding::None),
+            'L' | 'u' | 'U' => match self.lex_encoding_prefix() {
+                Some(encoding) if self.current_character == '\'' => {
+                    return self.lex_character_constant(start_index, encoding);
+                }
+                Some(encoding) => return self.lex_string_literal(start_index, encoding),
+                None => self.lex_identifier_or_keyword(start_index),
+            },
+            '_' | 'a'..='z' | 'A'..='Z' => self.lex_identifier_or_keyword(start_index),
+            '\\' if self.identifier_character(start_index, true).is_some() => {
+                self.lex_identifier_or_keyword(start_index)
+            }
+            character if character.is_xid_start() => self.lex_identifier_or_keyword(start_index),
+            '0'..='9' => {
+                let token = self.lex_number_constant(start_index);
+                return self.lex_pp_number_rest(token);
+            }
+            '\0' if self.past_end => TokenKind::Eof,
+            '\0' => {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let warning = "null character ignored";
+                let report = Report::warning()
+                    .with_message(warning)
+                    .with_warning(Warning::NullCharacter)
+                    .with_annotations(vec![Annotation::primary(span, warning)]);
+                self.diagnostic.report(report);
+
+                TokenKind::Trivia(Trivia::Whitespace)
+            }
             _ => {
-                let span = Span::new(start_index, self.index);
+                let span = Span::new(self.file_id, start_index, self.index);
 
                 let error = "unexpected token";
                 let report = Report::error()
                     .with_error_code(ErrorCode::E0001(self.current_character.to_string()))
-                    .with_annotations(vec![Annotation::primary(span, error.clone())]);
+                    .with_annotations(vec![Annotation::primary(span, error)]);
                 self.diagnostic.report(report);
 
-                return None;
+                TokenKind::Error
             }
         };
 
-        let span = Span::new(start_index, self.index);
-        let token = Token::new(kind, span);
+        let span = Span::new(self.file_id, start_index, self.index);
+        Token::new(kind, span)
+    }
+
+    /// Lexes the rest of a header name (C11 6.4.7), whose characters are taken
+    /// as written, so that neither escape sequences nor comments are
+    /// recognized inside it. Returns `false` without consuming anything if it
+    /// is not closed on the same line.
+    fn lex_header_name(&mut self) -> bool {
+        let close = if self.current_character == '<' {
+            '>'
+        } else {
+            '"'
+        };
 
-        Some(token)
+        let mut index = self.skip_line_splices(self.index);
+        loop {
+            if index >= self.text.len() {
+                return false;
+            }
+
+            match self.character_at(index) {
+                '\n' | '\r' => return false,
+                character if character == close => break,
+                character => index = self.skip_line_splices(index + character.len_utf8()),
+            }
+        }
+
+        while self.index <= index {
+            self.advance();
+        }
+
+        true
     }
 
-    fn lex_string_literal(&mut self) -> Option<TokenKind> {
-        let start_index = self.index - 1;
+    fn lex_encoding_prefix(&mut self) -> Option<Encoding> {
+        let (encoding, length) = match (self.current_character, self.peek(0)) {
+            ('L', _) => (Encoding::Wide, 0),
+            ('U', _) => (Encoding::Utf32, 0),
+            ('u', '8') => (Encoding::Utf8, 1),
+            ('u', _) => (Encoding::Utf16, 0),
+            _ => return None,
+        };
+
+        if !matches!(self.peek(length), '\'' | '"') {
+            return None;
+        }
+
+        let start_index = self.index - self.current_character.len_utf8();
+        for _ in 0..=length {
+            self.advance();
+        }
+
+        // Unlike `u8` string literals, `u8` character constants are new in C23
+        if encoding == Encoding::Utf8
+            && self.current_character == '\''
+            && self.standard < Standard::C23
+        {
+            let span = Span::new(self.file_id, start_index, self.index - 1);
+
+            let warning = "`u8` character constants are a C23 extension";
+            let report = Report::warning()
+                .with_message(warning)
+                .with_warning(Warning::C23Extensions)
+                .with_annotations(vec![Annotation::primary(span, warning)]);
+            self.diagnostic.report(report);
+        }
+
+        Some(encoding)
+    }
+
+    fn lex_character_constant(&mut self, start_index: usize, encoding: Encoding) -> Token {
+        let mut valid = true;
+        let mut character_count = 0;
+        let mut code_units = Vec::new();
+
+        let mut next_char = self.peek(0);
+        while next_char != '\'' {
+            if next_char == '\n' || self.has_reached_end() {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let error = "unclosed character constant";
+                let fixit =
+                    FixIt::insertion(span.shrink_to_hi(), "'", Applicability::MachineApplicable);
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0004)
+                    .with_annotations(vec![Annotation::primary(span, error)])
+                    .with_fixit(fixit);
+                self.diagnostic.report(report);
+
+                return Token::new(TokenKind::Error, span);
+            }
+
+            self.advance();
+            if self.current_character == '\\' {
+                valid &= self.lex_escape_sequence(encoding, &mut code_units);
+            } else {
+                encoding.encode(self.current_character, &mut code_units);
+            }
+
+            character_count += 1;
+            next_char = self.peek(0);
+        }
+
+        self.advance();
+
+        let span = Span::new(self.file_id, start_index, self.index);
+        if character_count == 0 {
+            let error = "empty character constant";
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0005)
+                .with_annotations(vec![Annotation::primary(span, error)]);
+            self.diagnostic.report(report);
+
+            return Token::new(TokenKind::Error, span);
+        }
+
+        if !valid {
+            return Token::new(TokenKind::Error, span);
+        }
+
+        if character_count > 1 {
+            let warning = "multi-character character constant";
+            let report = Report::warning()
+                .with_message(warning)
+                .with_warning(Warning::Multichar)
+                .with_annotations(vec![Annotation::primary(span, warning)]);
+            self.diagnostic.report(report);
+        }
+
+        // Multi-character constants follow GCC: narrow ones pack their code units
+        // big-endian into an `int`, prefixed ones keep only the last code unit.
+        let value = match encoding {
+            Encoding::None => code_units
+                .iter()
+                .fold(0u32, |value, code_unit| (value << 8) | code_unit),
+            _ => code_units.last().copied().unwrap_or_default(),
+        };
+
+        let kind = TokenKind::Literal(LiteralKind::Character(encoding));
+        Token::new(kind, span).with_value(LiteralValue::Character(value))
+    }
+
+    fn lex_string_literal(&mut self, start_index: usize, encoding: Encoding) -> Token {
+        let mut valid = true;
+        let mut code_units = Vec::new();
 
         let mut next_char = self.peek(0);
         while next_char != '"' {
-            if self.has_reached_end() {
-                let span = Span::new(start_index, self.index - 1);
+            if next_char == '\n' || self.has_reached_end() {
+                let span = Span::new(self.file_id, start_index, self.index);
 
                 let error = "unclosed string";
+                let fixit =
+                    FixIt::insertion(span.shrink_to_hi(), "\"", Applicability::MachineApplicable);
                 let report = Report::error()
                     .with_error_code(ErrorCode::E0003)
-                    .with_annotations(vec![Annotation::primary(span, error)]);
+                    .with_annotations(vec![Annotation::primary(span, error)])
+                    .with_fixit(fixit);
                 self.diagnostic.report(report);
 
-                return None;
+                return Token::new(TokenKind::Error, span);
             }
 
             self.advance();
+            if self.current_character == '\\' {
+                valid &= self.lex_escape_sequence(encoding, &mut code_units);
+            } else {
+                encoding.encode(self.current_character, &mut code_units);
+            }
+
             next_char = self.peek(0);
         }
 
         self.advance();
 
-        Some(TokenKind::Literal(LiteralKind::String))
+        let span = Span::new(self.file_id, start_index, self.index);
+        if !valid {
+            return Token::new(TokenKind::Error, span);
+        }
+
+        let kind = TokenKind::Literal(LiteralKind::String(encoding));
+        Token::new(kind, span).with_value(LiteralValue::String(code_units))
     }
 
-    fn lex_identifier_or_keyword(&mut self) -> TokenKind {
-        // TODO: Implement 'Universal character names'
+    fn lex_escape_sequence(&mut self, encoding: Encoding, code_units: &mut Vec<u32>) -> bool {
+        let start_index = self.index - 1;
 
-        let mut string = String::new();
-        string.push(self.current_character);
+        // The literal reports that it is unclosed
+        if self.has_reached_end() {
+            return false;
+        }
 
-        let mut next_char = self.peek(0);
-        while next_char == '_' || next_char.is_alphabetic() || next_char.is_ascii_digit() {
-            self.advance();
-            string.push(next_char);
+        self.advance();
+        let value = match self.current_character {
+            '\'' | '"' | '?' | '\\' => self.current_character as u64,
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'n' => 0x0a,
+            'r' => 0x0d,
+            't' => 0x09,
+            'v' => 0x0b,
+            '0'..='7' => {
+                let mut value = self.current_character as u64 - '0' as u64;
+                for _ in 0..2 {
+                    let next_char = self.peek(0);
+                    if !('0'..='7').contains(&next_char) {
+                        break;
+                    }
 
-            next_char = self.peek(0);
+                    self.advance();
+                    value = (value << 3) | (next_char as u64 - '0' as u64);
+                }
+
+                value
+            }
+            'x' => {
+                if !self.peek(0).is_ascii_hexdigit() {
+                    let span = Span::new(self.file_id, start_index, self.index);
+
+                    let error = "expected hexadecimal digits";
+                    let report = Report::error()
+                        .with_error_code(ErrorCode::E0007)
+                        .with_annotations(vec![Annotation::primary(span, error)]);
+                    self.diagnostic.report(report);
+
+                    return false;
+                }
+
+                let mut value = 0u64;
+                while let Some(digit) = self.peek(0).to_digit(16) {
+                    self.advance();
+                    value = value.saturating_mul(16).saturating_add(digit as u64);
+                }
+
+                value
+            }
+            'u' | 'U' => {
+                let digit_count = if self.current_character == 'u' { 4 } else { 8 };
+
+                let mut value = 0u32;
+                for _ in 0..digit_count {
+                    let Some(digit) = self.peek(0).to_digit(16) else {
+                        let span = Span::new(self.file_id, start_index, self.index);
+
+                        let error = format!("expected {} hexadecimal digits", digit_count);
+                        let report = Report::error()
+                            .with_error_code(ErrorCode::E0009)
+                            .with_annotations(vec![Annotation::primary(span, error)]);
+                        self.diagnostic.report(report);
+
+                        return false;
+                    };
+
+                    self.advance();
+                    value = (value << 4) | digit;
+                }
+
+                let Some(character) = char::from_u32(value) else {
+                    let span = Span::new(self.file_id, start_index, self.index);
+
+                    let error = format!("`{:#X}` is not a valid code point", value);
+                    let report = Report::error()
+                        .with_error_code(ErrorCode::E0009)
+                        .with_annotations(vec![Annotation::primary(span, error)]);
+                    self.diagnostic.report(report);
+
+                    return false;
+                };
+
+                encoding.encode(character, code_units);
+                return true;
+            }
+            _ => {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let error = "unknown escape sequence";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0006(self.current_character))
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                self.diagnostic.report(report);
+
+                return false;
+            }
+        };
+
+        if value > encoding.max_code_unit() as u64 {
+            let span = Span::new(self.file_id, start_index, self.index);
+
+            let error = format!(
+                "value does not fit into a code unit of {} bits",
+                encoding.code_unit_bits()
+            );
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0008)
+                .with_annotations(vec![Annotation::primary(span, error)]);
+            self.diagnostic.report(report);
+
+            return false;
+        }
+
+        code_units.push(value as u32);
+        true
+    }
+
+    fn lex_identifier_or_keyword(&mut self, start_index: usize) -> TokenKind {
+        if self.current_character == '\\' {
+            if let Some((_, end_index)) = self.identifier_character(start_index, true) {
+                while self.index < end_index {
+                    self.advance();
+                }
+            }
+        }
+
+        loop {
+            let next_char = self.peek(0);
+            if next_char.is_xid_continue() {
+                self.advance();
+                continue;
+            }
+
+            let index = self.skip_line_splices(self.index);
+            let Some((_, end_index)) = self.identifier_character(index, false) else {
+                break;
+            };
+
+            while self.index < end_index {
+                self.advance();
+            }
         }
 
+        let string = self.identifier_name(Span::new(self.file_id, start_index, self.index));
+
         match string.as_str() {
             "auto" => TokenKind::Identifier(Keyword::Auto),
             "break" => TokenKind::Identifier(Keyword::Break),
@@ -323,116 +858,525 @@ impl<'a> Lexer<'a> {
             "void" => TokenKind::Identifier(Keyword::Void),
             "volatile" => TokenKind::Identifier(Keyword::Volatile),
             "while" => TokenKind::Identifier(Keyword::While),
+            "_Bool" => TokenKind::Identifier(Keyword::Bool),
+            "_Complex" => TokenKind::Identifier(Keyword::Complex),
+            "_Imaginary" => TokenKind::Identifier(Keyword::Imaginary),
+            "_Alignas" if self.standard >= Standard::C11 => TokenKind::Identifier(Keyword::Alignas),
+            "_Alignof" if self.standard >= Standard::C11 => TokenKind::Identifier(Keyword::Alignof),
+            "_Atomic" if self.standard >= Standard::C11 => TokenKind::Identifier(Keyword::Atomic),
+            "_Generic" if self.standard >= Standard::C11 => TokenKind::Identifier(Keyword::Generic),
+            "_Noreturn" if self.standard >= Standard::C11 => {
+                TokenKind::Identifier(Keyword::Noreturn)
+            }
+            "_Static_assert" if self.standard >= Standard::C11 => {
+                TokenKind::Identifier(Keyword::StaticAssert)
+            }
+            "_Thread_local" if self.standard >= Standard::C11 => {
+                TokenKind::Identifier(Keyword::ThreadLocal)
+            }
+            "_BitInt" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::BitInt),
+            "alignas" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::Alignas),
+            "alignof" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::Alignof),
+            "bool" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::Bool),
+            "constexpr" if self.standard >= Standard::C23 => {
+                TokenKind::Identifier(Keyword::Constexpr)
+            }
+            "false" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::False),
+            "nullptr" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::Nullptr),
+            "static_assert" if self.standard >= Standard::C23 => {
+                TokenKind::Identifier(Keyword::StaticAssert)
+            }
+            "thread_local" if self.standard >= Standard::C23 => {
+                TokenKind::Identifier(Keyword::ThreadLocal)
+            }
+            "true" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::True),
+            "typeof" if self.standard >= Standard::C23 => TokenKind::Identifier(Keyword::Typeof),
+            "typeof_unqual" if self.standard >= Standard::C23 => {
+                TokenKind::Identifier(Keyword::TypeofUnqual)
+            }
             _ => TokenKind::Identifier(Keyword::None),
         }
     }
 
-    fn lex_number_constant(&mut self) -> Option<TokenKind> {
-        let start_index = self.index - 1;
+    /// Decodes the universal character name (C11 6.4.3) whose backslash is at
+    /// `index`, and returns its character together with the index after it.
+    fn universal_character_name(&self, index: usize) -> Option<(char, usize)> {
+        if self.character_at(index) != '\\' {
+            return None;
+        }
 
-        if self.current_character == '0' {
-            let next_char = self.peek(0);
-            if next_char == 'x' || next_char == 'X' {
-                self.advance();
+        let mut index = self.skip_line_splices(index + 1);
+        let digit_count = match self.character_at(index) {
+            'u' => 4,
+            'U' => 8,
+            _ => return None,
+        };
 
-                let mut next_char = self.peek(0);
-                if next_char.is_ascii_digit()
-                    || ('a'..='f').contains(&next_char)
-                    || ('A'..='F').contains(&next_char)
-                {
-                    while next_char.is_ascii_digit()
-                        || ('a'..='f').contains(&next_char)
-                        || ('A'..='F').contains(&next_char)
-                    {
-                        self.advance();
-                        next_char = self.peek(0);
-                    }
+        let mut value = 0u32;
+        for _ in 0..digit_count {
+            index = self.skip_line_splices(index + 1);
+            value = (value << 4) | self.character_at(index).to_digit(16)?;
+        }
+
+        Some((char::from_u32(value)?, index + 1))
+    }
+
+    /// Returns the character of a universal character name at `index` and the
+    /// index after it, if the character can start an identifier or, unless
+    /// `start` is set, continue one. Characters of the basic character set
+    /// cannot be spelled as universal character names.
+    fn identifier_character(&self, index: usize, start: bool) -> Option<(char, usize)> {
+        self.universal_character_name(index)
+            .filter(|(character, _)| {
+                let is_valid = if start {
+                    character.is_xid_start()
                 } else {
-                    let span = Span::new(start_index, self.index);
+                    character.is_xid_continue()
+                };
+                is_valid && u32::from(*character) >= 0xa0
+            })
+    }
 
-                    let error = "unclosed hexadecimal";
-                    let report = Report::error()
-                        .with_error_code(ErrorCode::E0002)
-                        .with_annotations(vec![Annotation::primary(span, error)]);
-                    self.diagnostic.report(report);
+    /// Returns the name of the identifier at `span`, with line splices removed
+    /// and universal character names decoded.
+    fn identifier_name(&self, span: Span) -> String {
+        let mut name = String::new();
+        let mut index = span.start();
+        while index < span.end() {
+            if let Some((character, end_index)) = self.universal_character_name(index) {
+                name.push(character);
+                index = self.skip_line_splices(end_index);
+                continue;
+            }
 
-                    return None;
-                }
-            } else if next_char.is_ascii_digit() {
-                let mut next_char = self.peek(0);
-                while ('0'..='7').contains(&next_char) {
-                    self.advance();
-                    next_char = self.peek(0);
-                }
+            let character = self.character_at(index);
+            name.push(character);
+            index = self.skip_line_splices(index + character.len_utf8());
+        }
+
+        name
+    }
+
+    fn lex_number_constant(&mut self, start_index: usize) -> Token {
+        let radix;
+        let mut value = Some(0);
+        let mut valid = true;
+
+        if self.current_character == '0' && matches!(self.peek(0), 'x' | 'X') {
+            self.advance();
+
+            let has_digits = self.peek(0).is_ascii_hexdigit()
+                || (self.peek(0) == '.' && self.peek(1).is_ascii_hexdigit())
+                || self.is_digit_separator(self.peek(0));
+            if !has_digits {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let error = "unclosed hexadecimal";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0002)
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                self.diagnostic.report(report);
+
+                return Token::new(TokenKind::Error, span);
+            }
+
+            radix = Radix::Hexadecimal;
+            valid &= self.lex_digits(radix, &mut value, false);
+
+            if matches!(self.peek(0), '.' | 'p' | 'P') {
+                let kind = self.lex_floating_constant(start_index, radix, valid);
+                return Token::new(kind, Span::new(self.file_id, start_index, self.index));
+            }
+        } else if self.current_character == '0' && matches!(self.peek(0), 'b' | 'B') {
+            self.advance();
+
+            let has_digits =
+                matches!(self.peek(0), '0' | '1') || self.is_digit_separator(self.peek(0));
+            if !has_digits {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let error = "expected binary digits";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0015)
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                self.diagnostic.report(report);
+
+                return Token::new(TokenKind::Error, span);
+            }
+
+            if self.standard < Standard::C23 {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let warning = "binary constants are a C23 extension";
+                let report = Report::warning()
+                    .with_message(warning)
+                    .with_warning(Warning::C23Extensions)
+                    .with_annotations(vec![Annotation::primary(span, warning)]);
+                self.diagnostic.report(report);
+            }
+
+            radix = Radix::Binary;
+            valid &= self.lex_digits(radix, &mut value, false);
+        } else if self.is_floating_constant_ahead() {
+            valid &= self.lex_digits(Radix::Decimal, &mut None, true);
+
+            let kind = self.lex_floating_constant(start_index, Radix::Decimal, valid);
+            return Token::new(kind, Span::new(self.file_id, start_index, self.index));
+        } else if self.current_character == '0' {
+            radix = Radix::Octal;
+            valid &= self.lex_digits(radix, &mut value, true);
+
+            if matches!(self.peek(0), '8' | '9') {
+                let digit_index = self.skip_line_splices(self.index);
+                let digit = self.peek(0);
+                self.advance();
+
+                let span = Span::new(self.file_id, digit_index, self.index);
+
+                let error = "not an octal digit";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0017(digit))
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                self.diagnostic.report(report);
+
+                self.lex_digits(Radix::Decimal, &mut None, true);
+                valid = false;
             }
         } else {
-            let mut next_char = self.peek(0);
-            while next_char.is_ascii_digit() {
+            radix = Radix::Decimal;
+            value = self.current_character.to_digit(10).map(u128::from);
+            valid &= self.lex_digits(radix, &mut value, true);
+        }
+
+        let suffix = self.lex_integer_suffix();
+
+        let span = Span::new(self.file_id, start_index, self.index);
+        let Some(suffix) = suffix.filter(|_| valid) else {
+            return Token::new(TokenKind::Error, span);
+        };
+
+        let constant = value
+            .map(|value| IntegerConstant::new(value, radix, suffix))
+            .filter(|constant| constant.integer_type(DataModel::default()).is_some());
+        let Some(constant) = constant else {
+            let error = "integer constant is too large";
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0014)
+                .with_annotations(vec![Annotation::primary(span, error)]);
+            self.diagnostic.report(report);
+
+            return Token::new(TokenKind::Error, span);
+        };
+
+        let kind = TokenKind::Literal(LiteralKind::Number);
+        Token::new(kind, span).with_value(LiteralValue::Integer(constant))
+    }
+
+    /// Lexes a sequence of digits in `radix` and accumulates them into `value`,
+    /// which becomes `None` on overflow. `has_digits` tells whether a digit was
+    /// already consumed, which decides if a leading digit separator is valid.
+    /// Returns `false` if a misplaced digit separator was reported.
+    /// Extends a number over the rest of its preprocessing number if
+    /// preprocessing numbers are lexed. Characters that do not belong to the
+    /// constant, like the `px` of `10px`, are reported as an invalid suffix.
+    fn lex_pp_number_rest(&mut self, token: Token) -> Token {
+        if !self.pp_numbers {
+            return token;
+        }
+
+        let suffix_index = self.skip_line_splices(self.index);
+        loop {
+            let next_char = self.peek(0);
+            if next_char == '\'' && self.peek(1).is_xid_continue() {
+                self.advance();
+                self.advance();
+                continue;
+            }
+
+            let is_exponent_sign = matches!(self.current_character, 'e' | 'E' | 'p' | 'P')
+                && matches!(next_char, '+' | '-');
+            if is_exponent_sign || next_char == '.' || next_char.is_xid_continue() {
+                self.advance();
+                continue;
+            }
+
+            let index = self.skip_line_splices(self.index);
+            let Some((_, end_index)) = self.identifier_character(index, false) else {
+                break;
+            };
+
+            while self.index < end_index {
                 self.advance();
-                next_char = self.peek(0);
             }
         }
 
-        // Lex suffix
-        let mut next_char = self.peek(0);
-        match next_char {
-            'u' | 'U' => {
+        let span = Span::new(self.file_id, token.span().start(), self.index);
+        if span == token.span() && token.kind() != TokenKind::Error {
+            return token;
+        }
+
+        // An invalid constant was already reported
+        if token.kind() != TokenKind::Error {
+            let suffix_span = Span::new(self.file_id, suffix_index, self.index);
+
+            let error = "invalid suffix";
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0036)
+                .with_annotations(vec![Annotation::primary(suffix_span, error)]);
+            self.diagnostic.report(report);
+        }
+
+        Token::new(TokenKind::Literal(LiteralKind::Number), span)
+    }
+
+    fn lex_digits(&mut self, radix: Radix, value: &mut Option<u128>, mut has_digits: bool) -> bool {
+        let mut valid = true;
+
+        loop {
+            let next_char = self.peek(0);
+            if let Some(digit) = next_char.to_digit(radix.value()) {
                 self.advance();
-                next_char = self.peek(0);
-                if next_char == 'l' || next_char == 'L' {
-                    self.advance();
-                    next_char = self.peek(0);
-                    if next_char == 'l' || next_char == 'L' {
-                        self.advance();
-                    }
+                *value = value
+                    .and_then(|value| value.checked_mul(u128::from(radix.value())))
+                    .and_then(|value| value.checked_add(u128::from(digit)));
+                has_digits = true;
+                continue;
+            }
+
+            if !self.is_digit_separator(next_char) {
+                break;
+            }
+
+            let separator_index = self.skip_line_splices(self.index);
+            self.advance();
+
+            let error = if !has_digits {
+                "digit separator cannot appear at the start of a number"
+            } else if self.peek(0) == '\'' {
+                "digit separators cannot be repeated"
+            } else if self.peek(0).to_digit(radix.value()).is_none() {
+                "digit separator cannot appear at the end of a number"
+            } else {
+                if self.standard < Standard::C23 {
+                    let span = Span::new(self.file_id, separator_index, self.index);
+
+                    let warning = "digit separators are a C23 extension";
+                    let report = Report::warning()
+                        .with_message(warning)
+                        .with_warning(Warning::C23Extensions)
+                        .with_annotations(vec![Annotation::primary(span, warning)]);
+                    self.diagnostic.report(report);
                 }
+
+                continue;
+            };
+
+            while self.peek(0) == '\'' {
+                self.advance();
+            }
+
+            let span = Span::new(self.file_id, separator_index, self.index);
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0016)
+                .with_annotations(vec![Annotation::primary(span, error)]);
+            self.diagnostic.report(report);
+
+            valid = false;
+        }
+
+        valid
+    }
+
+    /// Digit separators are also accepted before C23, with a warning, like
+    /// GCC and Clang do.
+    fn is_digit_separator(&self, character: char) -> bool {
+        character == '\''
+    }
+
+    /// Lexes the suffix of an integer constant. Returns `None` if a mixed-case
+    /// `lL` or `Ll` was reported.
+    fn lex_integer_suffix(&mut self) -> Option<IntegerSuffix> {
+        let mut unsigned = false;
+        if matches!(self.peek(0), 'u' | 'U') {
+            self.advance();
+            unsigned = true;
+        }
+
+        if self.standard >= Standard::C23
+            && matches!((self.peek(0), self.peek(1)), ('w', 'b') | ('W', 'B'))
+        {
+            self.advance();
+            self.advance();
+
+            if !unsigned && matches!(self.peek(0), 'u' | 'U') {
+                self.advance();
+                unsigned = true;
             }
-            'l' | 'L' => {
+
+            if unsigned {
+                return Some(IntegerSuffix::UnsignedBitPrecise);
+            }
+
+            return Some(IntegerSuffix::BitPrecise);
+        }
+
+        let mut long_count = 0;
+        let mut valid = true;
+        if matches!(self.peek(0), 'l' | 'L') {
+            let long_index = self.skip_line_splices(self.index);
+            let long = self.peek(0);
+            self.advance();
+            long_count += 1;
+
+            // Only `ll` and `LL` are valid, so a mixed case is reported
+            if matches!(self.peek(0), 'l' | 'L') {
+                valid = self.peek(0) == long;
                 self.advance();
-                next_char = self.peek(0);
-                if next_char == 'l' || next_char == 'L' {
-                    self.advance();
-                    next_char = self.peek(0);
-                }
+                long_count += 1;
+            }
 
-                if next_char == 'u' || next_char == 'U' {
-                    self.advance();
-                }
+            if !valid {
+                let span = Span::new(self.file_id, long_index, self.index);
+
+                let error = "the letters of `ll` must have the same case";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0015)
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                self.diagnostic.report(report);
             }
-            _ => {}
         }
 
-        Some(TokenKind::Literal(LiteralKind::Number))
+        if !unsigned && long_count > 0 && matches!(self.peek(0), 'u' | 'U') {
+            self.advance();
+            unsigned = true;
+        }
+
+        let suffix = match (unsigned, long_count) {
+            (false, 0) => IntegerSuffix::None,
+            (true, 0) => IntegerSuffix::Unsigned,
+            (false, 1) => IntegerSuffix::Long,
+            (true, 1) => IntegerSuffix::UnsignedLong,
+            (false, _) => IntegerSuffix::LongLong,
+            (true, _) => IntegerSuffix::UnsignedLongLong,
+        };
+
+        valid.then_some(suffix)
+    }
+
+    fn is_floating_constant_ahead(&self) -> bool {
+        let mut offset = 0;
+        while self.peek(offset).is_ascii_digit() || self.is_digit_separator(self.peek(offset)) {
+            offset += 1;
+        }
+
+        matches!(self.peek(offset), '.' | 'e' | 'E')
+    }
+
+    fn lex_floating_constant(
+        &mut self,
+        start_index: usize,
+        radix: Radix,
+        mut valid: bool,
+    ) -> TokenKind {
+        if self.peek(0) == '.' {
+            self.advance();
+        }
+
+        if self.current_character == '.' {
+            valid &= self.lex_digits(radix, &mut None, false);
+        }
+
+        let exponent_characters = match radix {
+            Radix::Hexadecimal => ['p', 'P'],
+            _ => ['e', 'E'],
+        };
+        if exponent_characters.contains(&self.peek(0)) {
+            self.advance();
+            if matches!(self.peek(0), '+' | '-') {
+                self.advance();
+            }
+
+            if !self.peek(0).is_ascii_digit() {
+                let span = Span::new(self.file_id, start_index, self.index);
+
+                let error = "expected exponent digits";
+                let report = Report::error()
+                    .with_error_code(ErrorCode::E0010)
+                    .with_annotations(vec![Annotation::primary(span, error)]);
+                self.diagnostic.report(report);
+
+                return TokenKind::Error;
+            }
+
+            valid &= self.lex_digits(Radix::Decimal, &mut None, false);
+        } else if radix == Radix::Hexadecimal {
+            let span = Span::new(self.file_id, start_index, self.index);
+
+            let error = "expected binary exponent";
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0011)
+                .with_annotations(vec![Annotation::primary(span, error)]);
+            self.diagnostic.report(report);
+
+            return TokenKind::Error;
+        }
+
+        if matches!(self.peek(0), 'f' | 'F' | 'l' | 'L') {
+            self.advance();
+        }
+
+        if !valid {
+            return TokenKind::Error;
+        }
+
+        TokenKind::Literal(LiteralKind::Float)
     }
 
     // Lexer specific
     fn advance(&mut self) {
-        if self.has_reached_end() {
+        self.index = self.skip_line_splices(self.index);
+
+        self.past_end = self.has_reached_end();
+        if self.past_end {
             self.current_character = '\0';
             return;
         }
 
-        if self.current_character == '\n' {
-            self.line += 1;
-            self.column = 0;
-        }
+        self.current_character = self.character_at(self.index);
+        self.index += self.current_character.len_utf8();
+    }
 
-        if self.current_character == '\t' {
-            self.column += 1;
+    fn peek(&self, extra_offset: usize) -> char {
+        let mut index = self.skip_line_splices(self.index);
+        for _ in 0..extra_offset {
+            index += self.character_at(index).len_utf8();
+            index = self.skip_line_splices(index);
         }
 
-        self.column += 1;
+        self.character_at(index)
+    }
 
-        self.current_character = self.text.as_bytes()[self.index] as char;
-        self.index += 1;
+    fn character_at(&self, index: usize) -> char {
+        self.text
+            .get(index..)
+            .and_then(|text| text.chars().next())
+            .unwrap_or('\0')
     }
 
-    fn peek(&self, extra_offset: usize) -> char {
-        if (self.index + extra_offset) >= self.text.len() {
-            return '\0';
+    /// Skips every backslash-newline pair starting at `index`, as described by
+    /// translation phase 2. Returns the index of the next character that is
+    /// not part of a line splice.
+    fn skip_line_splices(&self, mut index: usize) -> usize {
+        let bytes = self.text.as_bytes();
+        while index < bytes.len() && bytes[index] == b'\\' {
+            match bytes.get(index + 1) {
+                Some(b'\n') => index += 2,
+                Some(b'\r') if bytes.get(index + 2) == Some(&b'\n') => index += 3,
+                _ => break,
+            }
         }
 
-        self.text.as_bytes()[self.index + extra_offset] as char
+        index
     }
 
     fn skip_whitespace(&mut self) {
@@ -446,6 +1390,22 @@ impl<'a> Lexer<'a> {
     }
 
     fn has_reached_end(&self) -> bool {
-        self.index >= self.text.len()
+        self.skip_line_splices(self.index) >= self.text.len()
+    }
+}
+
+/// Lexes one token at a time, so that a preprocessor can act on a directive
+/// before the lines after it are lexed. Unlike [`Lexer::lex`], line splices in
+/// front of a token are not returned as trivia.
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.has_reached_end() || self.diagnostic.has_reached_error_limit() {
+            return None;
+        }
+
+        let token = self.next_token();
+        (token.kind() != TokenKind::Eof).then_some(token)
     }
 }
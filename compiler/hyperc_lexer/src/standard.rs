@@ -0,0 +1,38 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+/// The revision of the C standard the source is compiled against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Standard {
+    C99,
+    C11,
+    #[default]
+    C17,
+    C23,
+}
+
+impl Standard {
+    /// Parses the value of a `-std=` flag, accepting the same names as GCC.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "c99" | "c9x" | "iso9899:1999" => Some(Standard::C99),
+            "c11" | "c1x" | "iso9899:2011" => Some(Standard::C11),
+            "c17" | "c18" | "iso9899:2017" | "iso9899:2018" => Some(Standard::C17),
+            "c23" | "c2x" | "iso9899:2024" => Some(Standard::C23),
+            _ => None,
+        }
+    }
+
+    /// The value of `__STDC_VERSION__` for this standard.
+    pub fn version(&self) -> u64 {
+        match *self {
+            Standard::C99 => 199901,
+            Standard::C11 => 201112,
+            Standard::C17 => 201710,
+            Standard::C23 => 202311,
+        }
+    }
+}
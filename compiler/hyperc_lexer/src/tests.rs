@@ -7,6 +7,15 @@
 #![cfg(test)]
 
 use super::*;
+use crate::token::IntegerType;
+
+use hyperc_diagnostics::{
+    fixit::apply_fixits,
+    registry::EXPLANATIONS,
+    report::Severity,
+    sink::MemorySink,
+    warnings::{Warning, WarningSettings},
+};
 
 use paste::paste;
 use rand::distributions::{Alphanumeric, DistString};
@@ -24,8 +33,9 @@ macro_rules! test_keywords {
                 fn [<token_ $keyword>]() {
                     let text = concat!(stringify!($keyword), " ", stringify!($keyword));
 
-                    let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-                    let mut lexer = Lexer::new(&diagnostic, text);
+                    let diagnostic = Diagnostic::new();
+                    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+                    let mut lexer = Lexer::new(&diagnostic, file_id, text);
                     let tokens = lexer.lex().unwrap();
 
                     assert_eq!(tokens.len(), 2);
@@ -44,8 +54,9 @@ macro_rules! test_keywords {
                 )*
             );
 
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-            let mut lexer = Lexer::new(&diagnostic, text);
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text);
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), count!($($keyword)*));
@@ -104,8 +115,9 @@ macro_rules! test_punctuators {
                 fn [<token_ $name>]() {
                     let text = concat!($punctuator, " ", $punctuator);
 
-                    let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-                    let mut lexer = Lexer::new(&diagnostic, text);
+                    let diagnostic = Diagnostic::new();
+                    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+                    let mut lexer = Lexer::new(&diagnostic, file_id, text);
                     let tokens = lexer.lex().unwrap();
 
                     assert_eq!(tokens.len(), 2);
@@ -127,8 +139,9 @@ macro_rules! test_punctuators {
         fn token_punctuators() {
             let text = concat!($($punctuator, " ", )*);
 
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-            let mut lexer = Lexer::new(&diagnostic, text);
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text);
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), count!($($punctuator)*));
@@ -210,8 +223,9 @@ fn token_identifier() {
         }
 
         let text = format!("{0} {0}", string);
-        let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-        let mut lexer = Lexer::new(&diagnostic, text.as_str());
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
         let tokens = lexer.lex().unwrap();
 
         assert_eq!(tokens.len(), 2);
@@ -236,8 +250,9 @@ fn test_decimal() {
         let number = i.to_string();
 
         let text = format!("{number} {number}");
-        let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-        let mut lexer = Lexer::new(&diagnostic, text.as_str());
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
         let tokens = lexer.lex().unwrap();
 
         assert_eq!(tokens.len(), 2);
@@ -259,8 +274,9 @@ fn test_decimal() {
             let number = format!("{}{}", i, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -283,8 +299,9 @@ fn test_decimal() {
             let number = format!("{}{}", i, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -308,8 +325,9 @@ fn test_octal() {
         let number = format!("0{}", j);
 
         let text = format!("{number} {number}");
-        let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-        let mut lexer = Lexer::new(&diagnostic, text.as_str());
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
         let tokens = lexer.lex().unwrap();
 
         assert_eq!(tokens.len(), 2);
@@ -331,8 +349,9 @@ fn test_octal() {
             let number = format!("0{}{}", j, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -355,8 +374,9 @@ fn test_octal() {
             let number = format!("0{}{}", j, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -379,8 +399,9 @@ fn test_octal() {
 fn test_octal_fail() {
     let text = "08 08";
 
-    let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-    let mut lexer = Lexer::new(&diagnostic, text);
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
     let tokens = lexer.lex().unwrap();
 
     assert_eq!(tokens.len(), 2);
@@ -402,8 +423,9 @@ fn test_hexadecimal_lower() {
         let number = format!("{:#x}", i);
 
         let text = format!("{number} {number}");
-        let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-        let mut lexer = Lexer::new(&diagnostic, text.as_str());
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
         let tokens = lexer.lex().unwrap();
 
         assert_eq!(tokens.len(), 2);
@@ -425,8 +447,9 @@ fn test_hexadecimal_lower() {
             let number = format!("{:#x}{}", i, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -449,8 +472,9 @@ fn test_hexadecimal_lower() {
             let number = format!("{:#x}{}", i, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -473,8 +497,9 @@ fn test_hexadecimal_lower() {
 fn test_hexadecimal_lower_fail() {
     let text = "0xgg 0xgg";
 
-    let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-    let mut lexer = Lexer::new(&diagnostic, text);
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
     let tokens = lexer.lex().unwrap();
 
     assert_eq!(tokens.len(), 2);
@@ -496,8 +521,9 @@ fn test_hexadecimal_upper() {
         let number = format!("{:#X}", i);
 
         let text = format!("{number} {number}");
-        let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-        let mut lexer = Lexer::new(&diagnostic, text.as_str());
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
         let tokens = lexer.lex().unwrap();
 
         assert_eq!(tokens.len(), 2);
@@ -519,8 +545,9 @@ fn test_hexadecimal_upper() {
             let number = format!("{:#X}{}", i, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -543,8 +570,9 @@ fn test_hexadecimal_upper() {
             let number = format!("{:#X}{}", i, suffix);
 
             let text = format!("{number} {number}");
-            let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-            let mut lexer = Lexer::new(&diagnostic, text.as_str());
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
             let tokens = lexer.lex().unwrap();
 
             assert_eq!(tokens.len(), 2);
@@ -567,8 +595,9 @@ fn test_hexadecimal_upper() {
 fn test_hexadecimal_upper_fail() {
     let text = "0xGG 0xGG";
 
-    let diagnostic = Diagnostic::new("test.c", unindent::unindent(text));
-    let mut lexer = Lexer::new(&diagnostic, text);
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
     let tokens = lexer.lex().unwrap();
 
     assert_eq!(tokens.len(), 2);
@@ -702,8 +731,9 @@ fn test_numbers() {
         hexadecimal_upper.join("\n")
     );
 
-    let diagnostic = Diagnostic::new("test.c", unindent::unindent(text.as_str()));
-    let mut lexer = Lexer::new(&diagnostic, text.as_str());
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
     let tokens = lexer.lex().unwrap();
 
     assert_eq!(tokens.len(), 7917);
@@ -745,3 +775,1204 @@ fn test_numbers() {
         i += 1;
     }
 }
+
+#[test]
+fn test_character_constants() {
+    let constants = [
+        ("'a'", Encoding::None),
+        ("'\\''", Encoding::None),
+        ("'\\n'", Encoding::None),
+        ("L'a'", Encoding::Wide),
+        ("u'a'", Encoding::Utf16),
+        ("U'a'", Encoding::Utf32),
+        ("u8'a'", Encoding::Utf8),
+        ("'ab'", Encoding::None),
+    ];
+
+    for (constant, encoding) in constants {
+        let text = format!("{constant} {constant}");
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[0].kind(),
+            TokenKind::Literal(LiteralKind::Character(encoding))
+        );
+        assert_eq!(
+            &text[tokens[0].span().start()..tokens[0].span().end()],
+            constant
+        );
+        assert_eq!(
+            tokens[1].kind(),
+            TokenKind::Literal(LiteralKind::Character(encoding))
+        );
+        assert_eq!(
+            &text[tokens[1].span().start()..tokens[1].span().end()],
+            constant
+        );
+    }
+}
+
+#[test]
+fn test_prefix_identifiers() {
+    let text = "L u U u8 u8a";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 5);
+    for token in tokens {
+        assert_eq!(token.kind(), TokenKind::Identifier(Keyword::None));
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_character_constant_unclosed_fail() {
+    let text = "'a\n'b'";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    lexer.lex().unwrap();
+}
+
+#[test]
+#[should_panic]
+fn test_character_constant_empty_fail() {
+    let text = "''";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    lexer.lex().unwrap();
+}
+
+#[test]
+fn test_escape_sequences() {
+    let literals = [
+        (r#""a\"b""#, LiteralValue::String(vec![0x61, 0x22, 0x62])),
+        (
+            r#""\'\"\?\\\a\b\f\n\r\t\v""#,
+            LiteralValue::String(vec![
+                0x27, 0x22, 0x3f, 0x5c, 0x07, 0x08, 0x0c, 0x0a, 0x0d, 0x09, 0x0b,
+            ]),
+        ),
+        (
+            r#""\0\12\101\1234""#,
+            LiteralValue::String(vec![0, 0o12, 0o101, 0o123, 0x34]),
+        ),
+        (r#""\x41\xff""#, LiteralValue::String(vec![0x41, 0xff])),
+        (
+            r#"u"\U0001F600""#,
+            LiteralValue::String(vec![0xd83d, 0xde00]),
+        ),
+        (r#"U"\U0001F600""#, LiteralValue::String(vec![0x1f600])),
+        (r#"L"\x12345678""#, LiteralValue::String(vec![0x12345678])),
+        (r"'\''", LiteralValue::Character(0x27)),
+        (r"'\x7f'", LiteralValue::Character(0x7f)),
+        (r"u'\u20AC'", LiteralValue::Character(0x20ac)),
+        (r"'ab'", LiteralValue::Character(0x6162)),
+    ];
+
+    for (literal, value) in literals {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(literal));
+        let mut lexer = Lexer::new(&diagnostic, file_id, literal);
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].value(), Some(&value));
+        assert_eq!(
+            &literal[tokens[0].span().start()..tokens[0].span().end()],
+            literal
+        );
+    }
+}
+
+#[test]
+fn test_escape_sequences_fail() {
+    let literals = [
+        r#""\q""#,
+        r#""\x""#,
+        r#""\xfff""#,
+        r#""\777""#,
+        r#"u"\x10000""#,
+        r#""\u12""#,
+        r#""\uD800""#,
+        r#""\U00110000""#,
+        r"'\z'",
+    ];
+
+    for literal in literals {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(literal));
+        let mut lexer = Lexer::new(&diagnostic, file_id, literal);
+
+        assert!(lexer.lex().is_err(), "{literal} should not lex");
+    }
+}
+
+#[test]
+fn test_escape_sequence_at_end_fail() {
+    for (literal, code) in [
+        (r#""a\"#, "E0003"),
+        (r"'\", "E0004"),
+        ("\"a\\\\\n", "E0003"),
+    ] {
+        let sink = MemorySink::new();
+        let diagnostic = Diagnostic::new().with_sink(sink.clone());
+        let file_id = diagnostic.add_file("test.c", literal);
+        let mut lexer = Lexer::new(&diagnostic, file_id, literal);
+        assert!(lexer.lex().is_err(), "{literal} should not lex");
+
+        // Only the unclosed literal is reported, not the missing escape
+        let codes = sink
+            .reports()
+            .iter()
+            .filter_map(|report| report.code().map(str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(codes, [code], "{literal:?}");
+    }
+}
+
+#[test]
+fn test_floating_constants() {
+    let constants = [
+        "3.14", "1e-9", ".5f", "0x1.8p3", "10.0L", "1.", "1.e5", "5E+10F", "0x.8P-1l", "0xAp0",
+        "09.5",
+    ];
+
+    for constant in constants {
+        let text = format!("{constant} {constant}");
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text.as_str()));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text.as_str());
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].kind(), TokenKind::Literal(LiteralKind::Float));
+        assert_eq!(
+            &text[tokens[0].span().start()..tokens[0].span().end()],
+            constant
+        );
+        assert_eq!(tokens[1].kind(), TokenKind::Literal(LiteralKind::Float));
+        assert_eq!(
+            &text[tokens[1].span().start()..tokens[1].span().end()],
+            constant
+        );
+    }
+}
+
+#[test]
+fn test_floating_constants_fail() {
+    let constants = ["1e+", "1e", ".5e-f", "0x1.8", "0x1p"];
+
+    for constant in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(constant));
+        let mut lexer = Lexer::new(&diagnostic, file_id, constant);
+
+        assert!(lexer.lex().is_err(), "{constant} should not lex");
+    }
+}
+
+#[test]
+fn test_comments() {
+    let text = "a // line comment\n/* block\n * comment */ b /**/c/*/ */ // end";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 3);
+    for (token, name) in tokens.iter().zip(["a", "b", "c"]) {
+        assert_eq!(token.kind(), TokenKind::Identifier(Keyword::None));
+        assert_eq!(&text[token.span().start()..token.span().end()], name);
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_comment_unterminated_fail() {
+    let text = "a /* b";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    lexer.lex().unwrap();
+}
+
+#[test]
+fn test_many_comments() {
+    let text = format!(
+        "a\n{}{}b",
+        "// comment\n".repeat(100_000),
+        "/**/".repeat(100_000)
+    );
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", &text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, &text);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(&text[tokens[1].span().start()..tokens[1].span().end()], "b");
+}
+
+#[test]
+fn test_line_splicing() {
+    let text = "ret\\\nurn \"a\\\nb\" // comment \\\n continued\n0x\\\r\n1F 1\\\n";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[0].kind(), TokenKind::Identifier(Keyword::Return));
+    assert_eq!(
+        &text[tokens[0].span().start()..tokens[0].span().end()],
+        "ret\\\nurn"
+    );
+    assert_eq!(
+        tokens[1].kind(),
+        TokenKind::Literal(LiteralKind::String(Encoding::None))
+    );
+    assert_eq!(
+        tokens[1].value(),
+        Some(&LiteralValue::String(vec![0x61, 0x62]))
+    );
+    assert_eq!(tokens[2].kind(), TokenKind::Literal(LiteralKind::Number));
+    assert_eq!(
+        &text[tokens[2].span().start()..tokens[2].span().end()],
+        "0x\\\r\n1F"
+    );
+    assert_eq!(tokens[3].kind(), TokenKind::Literal(LiteralKind::Number));
+    assert_eq!(&text[tokens[3].span().start()..tokens[3].span().end()], "1");
+}
+
+#[test]
+fn test_unicode() {
+    let text = "/* ünïcödé */ größe \"é€😀\" u'€' Δx_1 // 注释";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[0].kind(), TokenKind::Identifier(Keyword::None));
+    assert_eq!(
+        &text[tokens[0].span().start()..tokens[0].span().end()],
+        "größe"
+    );
+    assert_eq!(
+        tokens[1].value(),
+        Some(&LiteralValue::String(vec![
+            0xc3, 0xa9, 0xe2, 0x82, 0xac, 0xf0, 0x9f, 0x98, 0x80
+        ]))
+    );
+    assert_eq!(tokens[2].value(), Some(&LiteralValue::Character(0x20ac)));
+    assert_eq!(tokens[3].kind(), TokenKind::Identifier(Keyword::None));
+    assert_eq!(
+        &text[tokens[3].span().start()..tokens[3].span().end()],
+        "Δx_1"
+    );
+}
+
+#[test]
+fn test_universal_character_names() {
+    let text = r"caf\u00E9 \u00e9t\u00E9 x\U000000E9 café";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", text);
+    let tokens = Lexer::new(&diagnostic, file_id, text).lex().unwrap();
+
+    let symbols = tokens
+        .iter()
+        .map(|token| token.symbol().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, ["café", "été", "xé", "café"]);
+    assert!(tokens
+        .iter()
+        .all(|token| token.kind() == TokenKind::Identifier(Keyword::None)));
+    assert_eq!(tokens[0].span(), Span::new(file_id, 0, 9));
+}
+
+#[test]
+fn test_universal_character_names_fail() {
+    // Neither basic characters nor emoji can be spelled in identifiers
+    for text in [r"a\u0041", r"\U0001F600", r"a\u00E"] {
+        let sink = MemorySink::new();
+        let diagnostic = Diagnostic::new().with_sink(sink.clone());
+        let file_id = diagnostic.add_file("test.c", text);
+        assert!(Lexer::new(&diagnostic, file_id, text).lex().is_err());
+        assert_eq!(sink.reports()[0].code(), Some("E0001"), "{text}");
+    }
+}
+
+#[test]
+fn test_invalid_utf8() {
+    let bytes = b"int \xff main";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", String::from_utf8_lossy(bytes));
+    assert!(Lexer::from_bytes(&diagnostic, file_id, bytes).is_none());
+
+    let bytes = "int größe".as_bytes();
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", String::from_utf8_lossy(bytes));
+    let mut lexer = Lexer::from_bytes(&diagnostic, file_id, bytes).unwrap();
+    assert_eq!(lexer.lex().unwrap().len(), 2);
+}
+
+#[test]
+fn test_error_recovery() {
+    let text = "int @ a = 0x; \"abc\nchar c = ''; \"\\q\" 1e+ b /* c";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let error = lexer.lex().unwrap_err();
+
+    assert_eq!(error.error_count(), 7);
+
+    let errors = error
+        .tokens()
+        .iter()
+        .filter(|token| token.kind() == TokenKind::Error)
+        .map(|token| &text[token.span().start()..token.span().end()])
+        .collect::<Vec<_>>();
+    assert_eq!(errors, ["@", "0x", "\"abc", "''", "\"\\q\"", "1e+", "/* c"]);
+
+    let identifiers = error
+        .tokens()
+        .iter()
+        .filter(|token| token.kind() == TokenKind::Identifier(Keyword::None))
+        .count();
+    assert_eq!(identifiers, 3);
+}
+
+#[test]
+fn test_symbols() {
+    let text = "count co\\\nunt int 42 \"count\" + count";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 7);
+    assert_eq!(tokens[0].symbol(), Some(Symbol::intern("count")));
+    assert_eq!(tokens[0].symbol(), tokens[1].symbol());
+    assert_eq!(tokens[0].symbol(), tokens[6].symbol());
+    assert_eq!(tokens[2].symbol(), Some(Symbol::intern("int")));
+    assert_eq!(tokens[3].symbol(), Some(Symbol::intern("42")));
+    assert_eq!(tokens[4].symbol(), Some(Symbol::intern("\"count\"")));
+    assert_eq!(tokens[5].symbol(), None);
+}
+
+#[test]
+fn test_integer_values() {
+    let constants = [
+        ("0", 0, Radix::Octal, IntegerSuffix::None, IntegerType::Int),
+        (
+            "42",
+            42,
+            Radix::Decimal,
+            IntegerSuffix::None,
+            IntegerType::Int,
+        ),
+        (
+            "0777u",
+            0o777,
+            Radix::Octal,
+            IntegerSuffix::Unsigned,
+            IntegerType::UnsignedInt,
+        ),
+        (
+            "0xFFul",
+            0xff,
+            Radix::Hexadecimal,
+            IntegerSuffix::UnsignedLong,
+            IntegerType::UnsignedLong,
+        ),
+        (
+            "10LL",
+            10,
+            Radix::Decimal,
+            IntegerSuffix::LongLong,
+            IntegerType::LongLong,
+        ),
+        (
+            "5llu",
+            5,
+            Radix::Decimal,
+            IntegerSuffix::UnsignedLongLong,
+            IntegerType::UnsignedLongLong,
+        ),
+        (
+            "2147483647",
+            0x7fff_ffff,
+            Radix::Decimal,
+            IntegerSuffix::None,
+            IntegerType::Int,
+        ),
+        (
+            "2147483648",
+            0x8000_0000,
+            Radix::Decimal,
+            IntegerSuffix::None,
+            IntegerType::Long,
+        ),
+        (
+            "0x80000000",
+            0x8000_0000,
+            Radix::Hexadecimal,
+            IntegerSuffix::None,
+            IntegerType::UnsignedInt,
+        ),
+        (
+            "0xFFFFFFFFFFFFFFFF",
+            u64::MAX as u128,
+            Radix::Hexadecimal,
+            IntegerSuffix::None,
+            IntegerType::UnsignedLong,
+        ),
+        (
+            "18446744073709551615u",
+            u64::MAX as u128,
+            Radix::Decimal,
+            IntegerSuffix::Unsigned,
+            IntegerType::UnsignedLong,
+        ),
+    ];
+
+    for (text, value, radix, suffix, integer_type) in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text);
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len(), 1);
+
+        let Some(LiteralValue::Integer(constant)) = tokens[0].value() else {
+            panic!("{text} has no integer value");
+        };
+        assert_eq!(constant.value(), value);
+        assert_eq!(constant.radix(), radix);
+        assert_eq!(constant.suffix(), suffix);
+        assert_eq!(constant.integer_type(DataModel::Lp64), Some(integer_type));
+    }
+}
+
+#[test]
+fn test_integer_types_data_model() {
+    let constant = IntegerConstant::new(1 << 40, Radix::Decimal, IntegerSuffix::Long);
+
+    assert_eq!(
+        constant.integer_type(DataModel::Lp64),
+        Some(IntegerType::Long)
+    );
+    assert_eq!(
+        constant.integer_type(DataModel::Llp64),
+        Some(IntegerType::LongLong)
+    );
+    assert_eq!(
+        constant.integer_type(DataModel::Ilp32),
+        Some(IntegerType::LongLong)
+    );
+}
+
+#[test]
+fn test_integer_overflow_fail() {
+    let constants = [
+        "18446744073709551616",
+        "9223372036854775808",
+        "0x10000000000000000",
+        "9223372036854775808ll",
+        "340282366920938463463374607431768211456",
+    ];
+
+    for constant in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(constant));
+        let mut lexer = Lexer::new(&diagnostic, file_id, constant);
+
+        assert!(lexer.lex().is_err(), "{constant} should not lex");
+    }
+}
+
+#[test]
+fn test_standard_keywords() {
+    let keywords = [
+        ("_Bool", Keyword::Bool, Standard::C99),
+        ("_Complex", Keyword::Complex, Standard::C99),
+        ("_Imaginary", Keyword::Imaginary, Standard::C99),
+        ("_Alignas", Keyword::Alignas, Standard::C11),
+        ("_Alignof", Keyword::Alignof, Standard::C11),
+        ("_Atomic", Keyword::Atomic, Standard::C11),
+        ("_Generic", Keyword::Generic, Standard::C11),
+        ("_Noreturn", Keyword::Noreturn, Standard::C11),
+        ("_Static_assert", Keyword::StaticAssert, Standard::C11),
+        ("_Thread_local", Keyword::ThreadLocal, Standard::C11),
+        ("_BitInt", Keyword::BitInt, Standard::C23),
+        ("alignas", Keyword::Alignas, Standard::C23),
+        ("alignof", Keyword::Alignof, Standard::C23),
+        ("bool", Keyword::Bool, Standard::C23),
+        ("constexpr", Keyword::Constexpr, Standard::C23),
+        ("false", Keyword::False, Standard::C23),
+        ("nullptr", Keyword::Nullptr, Standard::C23),
+        ("static_assert", Keyword::StaticAssert, Standard::C23),
+        ("thread_local", Keyword::ThreadLocal, Standard::C23),
+        ("true", Keyword::True, Standard::C23),
+        ("typeof", Keyword::Typeof, Standard::C23),
+        ("typeof_unqual", Keyword::TypeofUnqual, Standard::C23),
+    ];
+
+    let standards = [Standard::C99, Standard::C11, Standard::C17, Standard::C23];
+    for (text, keyword, introduced) in keywords {
+        for standard in standards {
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+            let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(standard);
+            let tokens = lexer.lex().unwrap();
+
+            let expected = if standard >= introduced {
+                keyword
+            } else {
+                Keyword::None
+            };
+
+            assert_eq!(tokens.len(), 1);
+            assert_eq!(
+                tokens[0].kind(),
+                TokenKind::Identifier(expected),
+                "{text} in {standard:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_standard_names() {
+    assert_eq!(Standard::from_name("c99"), Some(Standard::C99));
+    assert_eq!(Standard::from_name("c11"), Some(Standard::C11));
+    assert_eq!(Standard::from_name("c17"), Some(Standard::C17));
+    assert_eq!(Standard::from_name("c18"), Some(Standard::C17));
+    assert_eq!(Standard::from_name("c23"), Some(Standard::C23));
+    assert_eq!(Standard::from_name("gnu89"), None);
+}
+
+#[test]
+fn test_binary() {
+    for i in 0..256 {
+        for suffix in ["", "u", "UL", "ll"] {
+            let number = format!("{:#b}{}", i, suffix);
+
+            let diagnostic = Diagnostic::new();
+            let file_id = diagnostic.add_file("test.c", unindent::unindent(number.as_str()));
+            let mut lexer =
+                Lexer::new(&diagnostic, file_id, number.as_str()).with_standard(Standard::C23);
+            let tokens = lexer.lex().unwrap();
+
+            assert_eq!(tokens.len(), 1);
+            assert_eq!(tokens[0].kind(), TokenKind::Literal(LiteralKind::Number));
+
+            let Some(LiteralValue::Integer(constant)) = tokens[0].value() else {
+                panic!("{number} has no integer value");
+            };
+            assert_eq!(constant.value(), i);
+            assert_eq!(constant.radix(), Radix::Binary);
+        }
+    }
+}
+
+#[test]
+fn test_binary_extension() {
+    let text = "0b101";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C17);
+    let tokens = lexer.lex().unwrap();
+
+    let Some(LiteralValue::Integer(constant)) = tokens[0].value() else {
+        panic!("{text} has no integer value");
+    };
+    assert_eq!(constant.value(), 0b101);
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].named_warning(), Some(Warning::C23Extensions));
+    assert_eq!(reports[0].primary_span(), Some(Span::new(file_id, 0, 2)));
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C23);
+    assert!(lexer.lex().is_ok());
+    assert!(sink.reports().is_empty());
+}
+
+#[test]
+fn test_pp_numbers() {
+    let text = "0x1e+x 1.2.3 .5e-1f 10px 1e 12";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_pp_numbers(true);
+    let tokens = lexer.lex().unwrap();
+
+    let spellings = tokens
+        .iter()
+        .map(|token| token.symbol().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(spellings, ["0x1e+x", "1.2.3", ".5e-1f", "10px", "1e", "12"]);
+
+    // Invalid preprocessing numbers have no value
+    let kinds = tokens.iter().map(Token::kind).collect::<Vec<_>>();
+    assert_eq!(kinds[2], TokenKind::Literal(LiteralKind::Float));
+    assert!(kinds
+        .iter()
+        .enumerate()
+        .all(|(index, kind)| index == 2 || *kind == TokenKind::Literal(LiteralKind::Number)));
+    assert!(tokens[..2].iter().all(|token| token.value().is_none()));
+    assert!(tokens[3..5].iter().all(|token| token.value().is_none()));
+    assert!(tokens[5].value().is_some());
+
+    let reports = sink
+        .reports()
+        .iter()
+        .map(|report| (report.code().unwrap().to_string(), report.primary_span()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reports,
+        [
+            (String::from("E0036"), Some(Span::new(file_id, 4, 6))),
+            (String::from("E0036"), Some(Span::new(file_id, 10, 12))),
+            (String::from("E0036"), Some(Span::new(file_id, 22, 24))),
+            (String::from("E0010"), Some(Span::new(file_id, 25, 27))),
+        ]
+    );
+}
+
+#[test]
+fn test_utf8_character_constant_extension() {
+    let text = "u8'x' u8\"x\"";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C17);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(
+        tokens[0].kind(),
+        TokenKind::Literal(LiteralKind::Character(Encoding::Utf8))
+    );
+    assert_eq!(
+        tokens[0].value(),
+        Some(&LiteralValue::Character(u32::from(b'x')))
+    );
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].named_warning(), Some(Warning::C23Extensions));
+    assert_eq!(reports[0].primary_span(), Some(Span::new(file_id, 0, 2)));
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C23);
+    assert!(lexer.lex().is_ok());
+    assert!(sink.reports().is_empty());
+}
+
+#[test]
+fn test_long_long_suffix_case_fail() {
+    for text in ["1lL", "1Ll", "1lLu", "1uLl"] {
+        let sink = MemorySink::new();
+        let diagnostic = Diagnostic::new().with_sink(sink.clone());
+        let file_id = diagnostic.add_file("test.c", text);
+        let error = Lexer::new(&diagnostic, file_id, text).lex().unwrap_err();
+
+        assert_eq!(error.tokens().len(), 1, "{text} should be one token");
+        assert_eq!(sink.reports()[0].code(), Some("E0015"));
+    }
+}
+
+#[test]
+fn test_digit_separators() {
+    let constants = [
+        ("0b1010'0101", 0b1010_0101),
+        ("1'000'000", 1_000_000),
+        ("0xDEAD'BEEF", 0xdead_beef),
+        ("0'7'7", 0o77),
+        ("1'2u", 12),
+    ];
+
+    for (text, value) in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C23);
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len(), 1);
+
+        let Some(LiteralValue::Integer(constant)) = tokens[0].value() else {
+            panic!("{text} has no integer value");
+        };
+        assert_eq!(constant.value(), value);
+    }
+
+    let text = "1'000.000'5e1'0";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C23);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].kind(), TokenKind::Literal(LiteralKind::Float));
+}
+
+#[test]
+fn test_digit_separators_extension() {
+    let text = "1010'0101";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C17);
+    let tokens = lexer.lex().unwrap();
+
+    assert_eq!(tokens.len(), 1);
+    let Some(LiteralValue::Integer(constant)) = tokens[0].value() else {
+        panic!("{text} has no integer value");
+    };
+    assert_eq!(constant.value(), 1010_0101);
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].named_warning(), Some(Warning::C23Extensions));
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C23);
+    assert!(lexer.lex().is_ok());
+    assert!(sink.reports().is_empty());
+}
+
+#[test]
+fn test_digit_separators_fail() {
+    let constants = ["1''0", "1'", "0b'1", "0x'FF", "0x1'", "1.'5", "1'.5"];
+
+    for constant in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(constant));
+        let mut lexer = Lexer::new(&diagnostic, file_id, constant).with_standard(Standard::C23);
+
+        assert!(lexer.lex().is_err(), "{constant} should not lex");
+    }
+}
+
+#[test]
+fn test_bit_precise_suffixes() {
+    let constants = [
+        ("0wb", IntegerSuffix::BitPrecise, IntegerType::BitInt(2)),
+        ("1WB", IntegerSuffix::BitPrecise, IntegerType::BitInt(2)),
+        ("255wb", IntegerSuffix::BitPrecise, IntegerType::BitInt(9)),
+        (
+            "0uwb",
+            IntegerSuffix::UnsignedBitPrecise,
+            IntegerType::UnsignedBitInt(1),
+        ),
+        (
+            "255wbU",
+            IntegerSuffix::UnsignedBitPrecise,
+            IntegerType::UnsignedBitInt(8),
+        ),
+        (
+            "0xFFFF'FFFF'FFFF'FFFF'FFFFuwb",
+            IntegerSuffix::UnsignedBitPrecise,
+            IntegerType::UnsignedBitInt(80),
+        ),
+    ];
+
+    for (text, suffix, integer_type) in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text).with_standard(Standard::C23);
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len(), 1);
+
+        let Some(LiteralValue::Integer(constant)) = tokens[0].value() else {
+            panic!("{text} has no integer value");
+        };
+        assert_eq!(constant.suffix(), suffix);
+        assert_eq!(constant.integer_type(DataModel::Lp64), Some(integer_type));
+    }
+}
+
+#[test]
+fn test_octal_invalid_digits_fail() {
+    let constants = ["08", "09", "0778", "01239"];
+
+    for constant in constants {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(constant));
+        let mut lexer = Lexer::new(&diagnostic, file_id, constant);
+        let error = lexer.lex().unwrap_err();
+
+        assert_eq!(
+            error.tokens().len(),
+            1,
+            "{constant} should be a single token"
+        );
+    }
+}
+
+#[test]
+fn test_trivia() {
+    let text = "int a; // comment\r\n\t/* block */ b \\\n+1\n";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+    let mut lexer = Lexer::new(&diagnostic, file_id, text).with_trivia(true);
+    let tokens = lexer.lex().unwrap();
+
+    let kinds = tokens.iter().map(|token| token.kind()).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Identifier(Keyword::Int),
+            TokenKind::Trivia(Trivia::Whitespace),
+            TokenKind::Identifier(Keyword::None),
+            TokenKind::Semicolon,
+            TokenKind::Trivia(Trivia::Whitespace),
+            TokenKind::Trivia(Trivia::LineComment),
+            TokenKind::Trivia(Trivia::Newline),
+            TokenKind::Trivia(Trivia::Whitespace),
+            TokenKind::Trivia(Trivia::BlockComment),
+            TokenKind::Trivia(Trivia::Whitespace),
+            TokenKind::Identifier(Keyword::None),
+            TokenKind::Trivia(Trivia::Whitespace),
+            TokenKind::Trivia(Trivia::LineSplice),
+            TokenKind::BinaryOperation(BinaryOperation::Plus),
+            TokenKind::Literal(LiteralKind::Number),
+            TokenKind::Trivia(Trivia::Newline),
+        ]
+    );
+}
+
+#[test]
+fn test_trivia_lossless() {
+    let texts = [
+        "",
+        "   ",
+        "int main() {\n    return 0;\n}\n",
+        "\\\nint\\\n a\\\n",
+        "a /* unterminated",
+        "\"unclosed\n'x' @ 0x // end",
+        "\r\n\r \t\r\n/**/ // a\\\nb\n",
+        "größe = u8\"ü\";",
+    ];
+
+    for text in texts {
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", unindent::unindent(text));
+        let mut lexer = Lexer::new(&diagnostic, file_id, text).with_trivia(true);
+        let tokens = match lexer.lex() {
+            Ok(tokens) => tokens,
+            Err(error) => error.into_tokens(),
+        };
+
+        let output = tokens
+            .iter()
+            .map(|token| &text[token.span().start()..token.span().end()])
+            .collect::<String>();
+        assert_eq!(output, text);
+    }
+}
+
+#[test]
+fn test_incremental_lexing() {
+    let text = "#define A 1\
++ 2
+int a = A;
+";
+
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("test.c", text);
+    let expected = Lexer::new(&diagnostic, file_id, text).lex().unwrap();
+
+    // Restart the lexer after every token, as a preprocessor does
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut lexer = Lexer::new(&diagnostic, file_id, text).with_offset(offset);
+        let Some(token) = lexer.next() else {
+            break;
+        };
+
+        tokens.push(token);
+        offset = lexer.offset();
+    }
+
+    let spans = |tokens: &[Token]| tokens.iter().map(Token::span).collect::<Vec<_>>();
+    assert_eq!(spans(&tokens), spans(&expected));
+    assert_eq!(tokens[3].symbol(), Some(Symbol::intern("1")));
+}
+
+#[test]
+fn test_header_names() {
+    let text = r#"<a//b.h> "C:\path\x.h" <a.h"#;
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let tokens = Lexer::new(&diagnostic, file_id, text)
+        .with_header_name(true)
+        .lex()
+        .unwrap();
+
+    let spellings = tokens
+        .iter()
+        .map(|token| &text[token.span().start()..token.span().end()])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spellings,
+        ["<a//b.h>", r#""C:\path\x.h""#, "<", "a", ".", "h"]
+    );
+    assert_eq!(tokens[0].kind(), TokenKind::HeaderName);
+    assert_eq!(tokens[1].kind(), TokenKind::HeaderName);
+    assert_eq!(tokens[2].kind(), TokenKind::LessThan);
+    assert!(sink.reports().is_empty());
+}
+
+#[test]
+fn test_multiple_files() {
+    let header = "int a;";
+    let source = "int b = @;";
+
+    let diagnostic = Diagnostic::new();
+    let header_id = diagnostic.add_file("test.h", header);
+    let source_id = diagnostic.add_file("test.c", source);
+
+    let mut lexer = Lexer::new(&diagnostic, header_id, header);
+    let header_tokens = lexer.lex().unwrap();
+    assert!(header_tokens
+        .iter()
+        .all(|token| token.span().file_id() == header_id));
+
+    let mut lexer = Lexer::new(&diagnostic, source_id, source);
+    let error = lexer.lex().unwrap_err();
+    assert_eq!(error.error_count(), 1);
+    assert!(error
+        .tokens()
+        .iter()
+        .all(|token| token.span().file_id() == source_id));
+}
+
+#[test]
+fn test_reports() {
+    let text = "int @ a = 0x; '";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    assert!(lexer.lex().is_err());
+
+    let reports = sink
+        .reports()
+        .iter()
+        .map(|report| {
+            (
+                report.severity(),
+                report.code().unwrap().to_string(),
+                report.message().unwrap().to_string(),
+                report.annotations()[0].span(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reports,
+        [
+            (
+                Severity::Error,
+                String::from("E0001"),
+                String::from("unexpected token, found `@`"),
+                Span::new(file_id, 4, 5)
+            ),
+            (
+                Severity::Error,
+                String::from("E0002"),
+                String::from("unclosed hexadecimal number"),
+                Span::new(file_id, 10, 12)
+            ),
+            (
+                Severity::Error,
+                String::from("E0004"),
+                String::from("unclosed character constant"),
+                Span::new(file_id, 14, 15)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_error_limit() {
+    let text = "@ a @ b @ c";
+
+    let diagnostic = Diagnostic::new()
+        .with_sink(MemorySink::new())
+        .with_max_errors(2);
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    let error = lexer.lex().unwrap_err();
+
+    assert_eq!(error.error_count(), 2);
+    assert_eq!(error.tokens().len(), 3);
+    assert!(diagnostic.has_reached_error_limit());
+}
+
+#[test]
+fn test_null_character() {
+    let text = "x\0y\0";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let tokens = Lexer::new(&diagnostic, file_id, text).lex().unwrap();
+
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(&text[tokens[1].span().start()..tokens[1].span().end()], "y");
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].named_warning(), Some(Warning::NullCharacter));
+
+    let tokens = Lexer::new(&diagnostic, file_id, text)
+        .with_trivia(true)
+        .lex()
+        .unwrap();
+    let kinds = tokens.iter().map(Token::kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Identifier(Keyword::None),
+            TokenKind::Trivia(Trivia::Whitespace),
+            TokenKind::Identifier(Keyword::None),
+            TokenKind::Trivia(Trivia::Whitespace),
+        ]
+    );
+}
+
+#[test]
+fn test_multichar_warning() {
+    let text = "'ab'";
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    assert!(lexer.lex().is_ok());
+
+    let reports = sink.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].severity(), Severity::Warning);
+    assert_eq!(reports[0].named_warning(), Some(Warning::Multichar));
+
+    let mut settings = WarningSettings::new();
+    settings.apply_option("no-multichar");
+
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_warning_settings(settings);
+    let file_id = diagnostic.add_file("test.c", text);
+    let mut lexer = Lexer::new(&diagnostic, file_id, text);
+    assert!(lexer.lex().is_ok());
+    assert!(sink.reports().is_empty());
+}
+
+#[test]
+fn test_unclosed_fixits() {
+    let texts = [
+        ("char *s = \"abc;\nint a;", "char *s = \"abc;\"\nint a;"),
+        ("char c = 'a\n", "char c = 'a'\n"),
+        ("int a; /* comment", "int a; /* comment*/"),
+    ];
+
+    for (text, fixed) in texts {
+        let diagnostic = Diagnostic::new().with_sink(MemorySink::new());
+        let file_id = diagnostic.add_file("test.c", text);
+        let mut lexer = Lexer::new(&diagnostic, file_id, text);
+        assert!(lexer.lex().is_err());
+
+        assert_eq!(apply_fixits(text, &diagnostic.fixits()), fixed);
+
+        let diagnostic = Diagnostic::new();
+        let file_id = diagnostic.add_file("test.c", fixed);
+        let mut lexer = Lexer::new(&diagnostic, file_id, fixed);
+        assert!(lexer.lex().is_ok(), "{fixed} should lex");
+    }
+}
+
+#[test]
+fn test_explanation_examples() {
+    // The later error codes are reported by the preprocessor
+    let lexer_codes = ErrorCode::ALL
+        .iter()
+        .map(ErrorCode::code)
+        .take_while(|code| *code != "E0018")
+        .collect::<Vec<_>>();
+
+    for (code, explanation) in EXPLANATIONS {
+        if !lexer_codes.contains(code) {
+            continue;
+        }
+
+        let examples = explanation
+            .split("```c\n")
+            .skip(1)
+            .map(|block| block.split("```").next().unwrap())
+            .collect::<Vec<_>>();
+        let (Some(erroneous), Some(fixed)) = (examples.first(), examples.last()) else {
+            continue;
+        };
+
+        let sink = MemorySink::new();
+        let diagnostic = Diagnostic::new().with_sink(sink.clone());
+        let file_id = diagnostic.add_file("test.c", erroneous);
+        let mut lexer = Lexer::new(&diagnostic, file_id, erroneous).with_standard(Standard::C23);
+        let _ = lexer.lex();
+        assert!(
+            sink.reports()
+                .iter()
+                .any(|report| report.code() == Some(*code)),
+            "the example of {code} should report {code}"
+        );
+
+        let sink = MemorySink::new();
+        let diagnostic = Diagnostic::new().with_sink(sink.clone());
+        let file_id = diagnostic.add_file("test.c", fixed);
+        let mut lexer = Lexer::new(&diagnostic, file_id, fixed).with_standard(Standard::C23);
+        assert!(lexer.lex().is_ok(), "the fix of {code} should lex");
+        assert!(sink.reports().is_empty());
+    }
+}
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: MIT
  */
 
-use hyperc_span::Span;
+use hyperc_span::{symbol::Symbol, Span};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BinaryOperation {
@@ -31,11 +31,51 @@ pub enum Delimiter {
     Brace,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    None,
+    Wide,
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl Encoding {
+    pub fn code_unit_bits(&self) -> u32 {
+        match *self {
+            Encoding::None | Encoding::Utf8 => 8,
+            Encoding::Utf16 => 16,
+            Encoding::Wide | Encoding::Utf32 => 32,
+        }
+    }
+
+    pub fn max_code_unit(&self) -> u32 {
+        u32::MAX >> (32 - self.code_unit_bits())
+    }
+
+    pub fn encode(&self, character: char, code_units: &mut Vec<u32>) {
+        match *self {
+            Encoding::None | Encoding::Utf8 => {
+                let mut buffer = [0; 4];
+                let bytes = character.encode_utf8(&mut buffer).bytes();
+                code_units.extend(bytes.map(u32::from));
+            }
+            Encoding::Utf16 => {
+                let mut buffer = [0; 2];
+                let units = character.encode_utf16(&mut buffer).iter();
+                code_units.extend(units.map(|&unit| u32::from(unit)));
+            }
+            Encoding::Wide | Encoding::Utf32 => code_units.push(character as u32),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LiteralKind {
     Number,
-    Character,
-    String,
+    Float,
+    Character(Encoding),
+    String(Encoding),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -75,11 +115,39 @@ pub enum Keyword {
     Void,
     Volatile,
     While,
+
+    Alignas,
+    Alignof,
+    Atomic,
+    BitInt,
+    Bool,
+    Complex,
+    Constexpr,
+    False,
+    Generic,
+    Imaginary,
+    Noreturn,
+    Nullptr,
+    StaticAssert,
+    ThreadLocal,
+    True,
+    Typeof,
+    TypeofUnqual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trivia {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+    LineSplice,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenKind {
     Eof,
+    Error,
 
     OpenDelimiter(Delimiter),
     CloseDelimiter(Delimiter),
@@ -113,17 +181,203 @@ pub enum TokenKind {
     Identifier(Keyword),
 
     Literal(LiteralKind),
+    /// A `<...>` or `"..."` header name, which is only lexed after `#include`.
+    HeaderName,
+
+    Trivia(Trivia),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Decimal,
+    Hexadecimal,
+}
+
+impl Radix {
+    pub fn value(&self) -> u32 {
+        match *self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+            Radix::Hexadecimal => 16,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerSuffix {
+    None,
+    Unsigned,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    BitPrecise,
+    UnsignedBitPrecise,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerType {
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    BitInt(u32),
+    UnsignedBitInt(u32),
+}
+
+impl IntegerType {
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            *self,
+            IntegerType::Int | IntegerType::Long | IntegerType::LongLong | IntegerType::BitInt(_)
+        )
+    }
+
+    pub fn max_value(&self, data_model: DataModel) -> u128 {
+        let bits = data_model.bits(*self);
+        if self.is_signed() {
+            u128::MAX >> (129 - bits)
+        } else {
+            u128::MAX >> (128 - bits)
+        }
+    }
+}
+
+/// The widths of the standard integer types on the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataModel {
+    Ilp32,
+    #[default]
+    Lp64,
+    Llp64,
+}
+
+impl DataModel {
+    pub fn bits(&self, integer_type: IntegerType) -> u32 {
+        match (*self, integer_type) {
+            (_, IntegerType::Int | IntegerType::UnsignedInt) => 32,
+            (DataModel::Lp64, IntegerType::Long | IntegerType::UnsignedLong) => 64,
+            (_, IntegerType::Long | IntegerType::UnsignedLong) => 32,
+            (_, IntegerType::LongLong | IntegerType::UnsignedLongLong) => 64,
+            (_, IntegerType::BitInt(bits) | IntegerType::UnsignedBitInt(bits)) => bits,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntegerConstant {
+    value: u128,
+    radix: Radix,
+    suffix: IntegerSuffix,
+}
+
+impl IntegerConstant {
+    pub fn new(value: u128, radix: Radix, suffix: IntegerSuffix) -> Self {
+        Self {
+            value,
+            radix,
+            suffix,
+        }
+    }
+
+    pub fn value(&self) -> u128 {
+        self.value
+    }
+
+    pub fn radix(&self) -> Radix {
+        self.radix
+    }
+
+    pub fn suffix(&self) -> IntegerSuffix {
+        self.suffix
+    }
+
+    /// Picks the type of the constant as described by C11 6.4.4.1p5: the first
+    /// type of the candidate list that can represent the value. Returns `None`
+    /// if the value fits none of them.
+    ///
+    /// Bit-precise constants get the narrowest `_BitInt(N)` that can hold the
+    /// value, as described by C23 6.4.4.1p6.
+    pub fn integer_type(&self, data_model: DataModel) -> Option<IntegerType> {
+        use IntegerType::*;
+
+        let value_bits = u128::BITS - self.value.leading_zeros();
+        match self.suffix {
+            IntegerSuffix::BitPrecise if value_bits < u128::BITS => {
+                return Some(BitInt(value_bits.max(1) + 1));
+            }
+            IntegerSuffix::BitPrecise => return None,
+            IntegerSuffix::UnsignedBitPrecise => return Some(UnsignedBitInt(value_bits.max(1))),
+            _ => {}
+        }
+
+        let candidates: &[IntegerType] = match (self.suffix, self.radix) {
+            (IntegerSuffix::None, Radix::Decimal) => &[Int, Long, LongLong],
+            (IntegerSuffix::None, _) => &[
+                Int,
+                UnsignedInt,
+                Long,
+                UnsignedLong,
+                LongLong,
+                UnsignedLongLong,
+            ],
+            (IntegerSuffix::Unsigned, _) => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+            (IntegerSuffix::Long, Radix::Decimal) => &[Long, LongLong],
+            (IntegerSuffix::Long, _) => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+            (IntegerSuffix::UnsignedLong, _) => &[UnsignedLong, UnsignedLongLong],
+            (IntegerSuffix::LongLong, Radix::Decimal) => &[LongLong],
+            (IntegerSuffix::LongLong, _) => &[LongLong, UnsignedLongLong],
+            (IntegerSuffix::UnsignedLongLong, _) => &[UnsignedLongLong],
+            (IntegerSuffix::BitPrecise | IntegerSuffix::UnsignedBitPrecise, _) => unreachable!(),
+        };
+
+        candidates
+            .iter()
+            .copied()
+            .find(|candidate| self.value <= candidate.max_value(data_model))
+    }
+}
+
+/// The decoded value of a literal. Strings and characters are stored as code
+/// units of their [`Encoding`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LiteralValue {
+    Integer(IntegerConstant),
+    Character(u32),
+    String(Vec<u32>),
 }
 
 #[derive(Clone, Debug)]
 pub struct Token {
     kind: TokenKind,
     span: Span,
+    symbol: Option<Symbol>,
+    value: Option<LiteralValue>,
 }
 
 impl Token {
     pub fn new(kind: TokenKind, span: Span) -> Self {
-        Self { kind, span }
+        Self {
+            kind,
+            span,
+            symbol: None,
+            value: None,
+        }
+    }
+
+    pub fn with_symbol(mut self, symbol: Symbol) -> Self {
+        self.symbol = Some(symbol);
+        self
+    }
+
+    pub fn with_value(mut self, value: LiteralValue) -> Self {
+        self.value = Some(value);
+        self
     }
 
     pub fn kind(&self) -> TokenKind {
@@ -133,4 +387,13 @@ impl Token {
     pub fn span(&self) -> Span {
         self.span
     }
+
+    /// The spelling of an identifier or literal, with line splices removed.
+    pub fn symbol(&self) -> Option<Symbol> {
+        self.symbol
+    }
+
+    pub fn value(&self) -> Option<&LiteralValue> {
+        self.value.as_ref()
+    }
 }
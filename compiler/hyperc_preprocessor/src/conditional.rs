@@ -0,0 +1,176 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{token::PpToken, Guard, Preprocessor};
+
+use hyperc_diagnostics::{annotation::Annotation, errors::ErrorCode, report::Report};
+use hyperc_lexer::token::TokenKind;
+use hyperc_span::Span;
+
+/// A conditional of the innermost file whose `#endif` has not been read yet.
+#[derive(Debug)]
+pub(crate) struct Conditional {
+    /// The span of the directive that started the conditional.
+    pub(crate) span: Span,
+    pub(crate) directive: String,
+    /// Whether one of the groups has been taken, so that the rest are skipped.
+    taken: bool,
+    has_else: bool,
+}
+
+impl Preprocessor<'_> {
+    /// Executes `#ifdef` or, if `defined` is `false`, `#ifndef`.
+    pub(crate) fn ifdef(&mut self, hash: &PpToken, line: &[PpToken], defined: bool) {
+        let name = self.macro_name(line);
+        let taken = name.is_some_and(|name| self.macros.contains_key(&name) == defined);
+
+        // Only `#ifndef` as the first directive of a file can start a guard
+        if let Some(file) = self.files.last_mut() {
+            if let (Guard::Start, Some(name), false) = (file.guard, name, defined) {
+                file.guard = Guard::Open(name);
+            }
+        }
+
+        self.start_conditional(hash, line, taken);
+    }
+
+    /// Executes `#if`.
+    pub(crate) fn if_(&mut self, hash: &PpToken, line: &[PpToken]) {
+        let taken = self.condition(line);
+        self.start_conditional(hash, line, taken);
+    }
+
+    /// Pushes the conditional started by the directive in `line`, and skips
+    /// its first group unless it is `taken`.
+    fn start_conditional(&mut self, hash: &PpToken, line: &[PpToken], taken: bool) {
+        let directive = self.spelling(&line[0].token);
+        let Some(file) = self.files.last_mut() else {
+            return;
+        };
+
+        file.conditionals.push(Conditional {
+            span: hash.span().to(line[0].span()),
+            directive,
+            taken,
+            has_else: false,
+        });
+
+        if !taken {
+            self.skip_group();
+        }
+    }
+
+    /// Executes `#elif`, `#elifdef`, `#elifndef` or `#else`, whose group is
+    /// only taken if no earlier group of the conditional was.
+    pub(crate) fn else_(&mut self, hash: &PpToken, line: &[PpToken]) {
+        let directive = self.spelling(&line[0].token);
+        let span = hash.span().to(line[0].span());
+
+        let Some(conditional) = self.files.last().and_then(|file| file.conditionals.last()) else {
+            self.error(
+                ErrorCode::E0031(directive),
+                span,
+                "no conditional to continue",
+            );
+            return;
+        };
+
+        let previous_taken = conditional.taken;
+        if conditional.has_else {
+            let report = Report::error()
+                .with_error_code(ErrorCode::E0033(directive))
+                .with_annotations(vec![
+                    Annotation::primary(span, "the `#else` group has to come last"),
+                    Annotation::secondary(conditional.span, "the conditional starts here"),
+                ]);
+            self.diagnostic.report(report);
+            self.skip_group();
+            return;
+        }
+
+        // The condition of a group is not evaluated once another was taken
+        let taken = !previous_taken
+            && match directive.as_str() {
+                "elifdef" | "elifndef" => {
+                    let defined = directive == "elifdef";
+                    self.macro_name(line)
+                        .is_some_and(|name| self.macros.contains_key(&name) == defined)
+                }
+                "elif" => self.condition(line),
+                _ => true,
+            };
+
+        let Some(file) = self.files.last_mut() else {
+            return;
+        };
+
+        // A guard covers the whole file, so it cannot have other groups
+        if file.conditionals.len() == 1 {
+            if let Guard::Open(_) = file.guard {
+                file.guard = Guard::None;
+            }
+        }
+
+        if let Some(conditional) = file.conditionals.last_mut() {
+            conditional.has_else |= directive == "else";
+            conditional.taken |= taken;
+        }
+
+        if !taken {
+            self.skip_group();
+        }
+    }
+
+    pub(crate) fn endif(&mut self, hash: &PpToken, line: &[PpToken]) {
+        let Some(file) = self.files.last_mut() else {
+            return;
+        };
+
+        if file.conditionals.pop().is_none() {
+            let directive = self.spelling(&line[0].token);
+            self.error(
+                ErrorCode::E0031(directive),
+                hash.span().to(line[0].span()),
+                "no conditional to end",
+            );
+            return;
+        }
+
+        if let (Guard::Open(guard), true) = (file.guard, file.conditionals.is_empty()) {
+            file.guard = Guard::Closed(guard);
+        }
+    }
+
+    /// Skips the lines of a group that is not taken, up to the `#elif`,
+    /// `#elifdef`, `#elifndef`, `#else` or `#endif` that continues its
+    /// conditional, which is then executed.
+    fn skip_group(&mut self) {
+        let mut depth = 0usize;
+        self.skipping = true;
+
+        while let Some(token) = self.lex_token(false, false) {
+            if !token.at_line_start || token.kind() != TokenKind::PoundSign {
+                continue;
+            }
+
+            let line = self.read_line();
+            let name = line.first().and_then(PpToken::identifier);
+            match name.as_ref().map(|name| name.as_str()) {
+                Some("if" | "ifdef" | "ifndef") => depth += 1,
+                Some("endif") if depth > 0 => depth -= 1,
+                Some("elif" | "elifdef" | "elifndef" | "else" | "endif") if depth == 0 => {
+                    self.skipping = false;
+                    self.execute(&token, &line);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        // The end of the file reports the unterminated conditional
+        self.skipping = false;
+    }
+}
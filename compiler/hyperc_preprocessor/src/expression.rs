@@ -0,0 +1,421 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{
+    macros::{is_close_parenthesis, is_open_parenthesis},
+    token::PpToken,
+    Preprocessor,
+};
+
+use hyperc_diagnostics::errors::ErrorCode;
+use hyperc_lexer::token::{
+    BinaryOperation, DataModel, Delimiter, Encoding, IntegerConstant, IntegerSuffix, Keyword,
+    LiteralKind, LiteralValue, Radix, Token, TokenKind,
+};
+use hyperc_span::{symbol::Symbol, Span};
+
+/// The value of a condition, where every integer has the type `intmax_t` or
+/// `uintmax_t` (C11 6.10.1p4).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    Signed(i64),
+    Unsigned(u64),
+}
+
+impl Value {
+    fn from_bool(value: bool) -> Self {
+        Value::Signed(i64::from(value))
+    }
+
+    fn is_true(self) -> bool {
+        self.as_unsigned() != 0
+    }
+
+    fn is_unsigned(self) -> bool {
+        matches!(self, Value::Unsigned(_))
+    }
+
+    fn as_signed(self) -> i64 {
+        match self {
+            Value::Signed(value) => value,
+            Value::Unsigned(value) => value as i64,
+        }
+    }
+
+    fn as_unsigned(self) -> u64 {
+        match self {
+            Value::Signed(value) => value as u64,
+            Value::Unsigned(value) => value,
+        }
+    }
+
+    /// Converts both operands to `uintmax_t` if either of them is unsigned,
+    /// as done by the usual arithmetic conversions.
+    fn arithmetic(
+        self,
+        rhs: Value,
+        signed: fn(i64, i64) -> i64,
+        unsigned: fn(u64, u64) -> u64,
+    ) -> Value {
+        if self.is_unsigned() || rhs.is_unsigned() {
+            Value::Unsigned(unsigned(self.as_unsigned(), rhs.as_unsigned()))
+        } else {
+            Value::Signed(signed(self.as_signed(), rhs.as_signed()))
+        }
+    }
+
+    fn compare(
+        self,
+        rhs: Value,
+        signed: fn(&i64, &i64) -> bool,
+        unsigned: fn(&u64, &u64) -> bool,
+    ) -> Value {
+        if self.is_unsigned() || rhs.is_unsigned() {
+            Value::from_bool(unsigned(&self.as_unsigned(), &rhs.as_unsigned()))
+        } else {
+            Value::from_bool(signed(&self.as_signed(), &rhs.as_signed()))
+        }
+    }
+
+    /// Shifts by `amount` bits. Shifting by the width or more, or by a
+    /// negative amount, gives 0, or -1 for a negative value shifted right.
+    fn shift(self, amount: Value, left: bool) -> Value {
+        let amount = u32::try_from(amount.as_unsigned()).unwrap_or(u32::MAX);
+        match self {
+            Value::Signed(value) if left => Value::Signed(value.checked_shl(amount).unwrap_or(0)),
+            Value::Signed(value) => Value::Signed(value.checked_shr(amount).unwrap_or(value >> 63)),
+            Value::Unsigned(value) if left => {
+                Value::Unsigned(value.checked_shl(amount).unwrap_or(0))
+            }
+            Value::Unsigned(value) => Value::Unsigned(value.checked_shr(amount).unwrap_or(0)),
+        }
+    }
+}
+
+impl Preprocessor<'_> {
+    /// Evaluates the condition of `#if` or `#elif`, whose name is at the start
+    /// of `line`, as described by C11 6.10.1. An invalid condition is reported
+    /// and is false.
+    pub(crate) fn condition(&mut self, line: &[PpToken]) -> bool {
+        let Some(tokens) = self.replace_defined(&line[1..]) else {
+            return false;
+        };
+
+        // The condition is converted into tokens after its expansion
+        let tokens = self.expand_tokens(&tokens);
+        let mut valid = true;
+        for token in &tokens {
+            valid &= self.report_token(token);
+        }
+
+        if !valid {
+            return false;
+        }
+
+        let end = tokens
+            .last()
+            .unwrap_or(&line[line.len() - 1])
+            .span()
+            .shrink_to_hi();
+
+        let mut evaluator = Evaluator {
+            preprocessor: self,
+            tokens: &tokens,
+            index: 0,
+            end,
+            evaluated: true,
+        };
+        let Some(value) = evaluator.conditional() else {
+            return false;
+        };
+
+        if let Some(token) = tokens.get(evaluator.index) {
+            self.error(ErrorCode::E0034, token.span(), "expected an operator");
+            return false;
+        }
+
+        value.is_true()
+    }
+
+    /// Replaces `defined X` and `defined(X)` by 1 if `X` is a macro and by 0
+    /// otherwise, which is done before the condition is macro-expanded.
+    fn replace_defined(&self, tokens: &[PpToken]) -> Option<Vec<PpToken>> {
+        let mut replaced = Vec::new();
+        let mut index = 0;
+        while let Some(token) = tokens.get(index) {
+            if !token.is_identifier("defined") {
+                replaced.push(token.clone());
+                index += 1;
+                continue;
+            }
+
+            let parenthesized = tokens.get(index + 1).is_some_and(is_open_parenthesis);
+            let name_index = index + 1 + usize::from(parenthesized);
+            let end = name_index + usize::from(parenthesized);
+
+            let name = tokens.get(name_index).and_then(PpToken::identifier);
+            let is_closed = !parenthesized || tokens.get(end).is_some_and(is_close_parenthesis);
+            let (Some(name), true) = (name, is_closed) else {
+                let last = &tokens[end.min(tokens.len() - 1)];
+                self.error(
+                    ErrorCode::E0034,
+                    token.span().to(last.span()),
+                    "expected a macro name after `defined`",
+                );
+                return None;
+            };
+
+            let span = token.span().to(tokens[end].span());
+            let mut number = PpToken::new(number(self.macros.contains_key(&name), span));
+            number.leading_space = token.leading_space;
+            replaced.push(number);
+            index = end + 1;
+        }
+
+        Some(replaced)
+    }
+}
+
+/// Evaluates the tokens of a condition by precedence climbing.
+struct Evaluator<'p, 'a> {
+    preprocessor: &'p Preprocessor<'a>,
+    tokens: &'p [PpToken],
+    index: usize,
+    /// The end of the condition, where a missing operand is reported.
+    end: Span,
+    /// Whether the current operand is evaluated, rather than skipped by `&&`,
+    /// `||` or `?:`, so that it cannot divide by zero.
+    evaluated: bool,
+}
+
+impl Evaluator<'_, '_> {
+    fn conditional(&mut self) -> Option<Value> {
+        let condition = self.binary(1)?;
+        if !self.eat(TokenKind::QuestionMark) {
+            return Some(condition);
+        }
+
+        let evaluated = self.evaluated;
+        self.evaluated = evaluated && condition.is_true();
+        let then = self.conditional()?;
+        if !self.eat(TokenKind::Colon) {
+            self.expected("expected `:`");
+            return None;
+        }
+
+        self.evaluated = evaluated && !condition.is_true();
+        let otherwise = self.conditional()?;
+        self.evaluated = evaluated;
+
+        let value = if condition.is_true() { then } else { otherwise };
+        if then.is_unsigned() || otherwise.is_unsigned() {
+            return Some(Value::Unsigned(value.as_unsigned()));
+        }
+
+        Some(value)
+    }
+
+    /// Evaluates the binary operators that bind at least as tightly as
+    /// `precedence`.
+    fn binary(&mut self, precedence: u8) -> Option<Value> {
+        let mut lhs = self.unary()?;
+        while let Some(operator) = self.tokens.get(self.index) {
+            let kind = operator.kind();
+            let Some(operator_precedence) = binary_precedence(kind) else {
+                break;
+            };
+            if operator_precedence < precedence {
+                break;
+            }
+            self.index += 1;
+
+            let evaluated = self.evaluated;
+            match kind {
+                TokenKind::LogicalAnd => self.evaluated &= lhs.is_true(),
+                TokenKind::LogicalOr => self.evaluated &= !lhs.is_true(),
+                _ => {}
+            }
+            let rhs = self.binary(operator_precedence + 1)?;
+            self.evaluated = evaluated;
+
+            lhs = self.apply(kind, lhs, rhs, operator.span())?;
+        }
+
+        Some(lhs)
+    }
+
+    fn apply(&self, kind: TokenKind, lhs: Value, rhs: Value, span: Span) -> Option<Value> {
+        let value = match kind {
+            TokenKind::LogicalOr => Value::from_bool(lhs.is_true() || rhs.is_true()),
+            TokenKind::LogicalAnd => Value::from_bool(lhs.is_true() && rhs.is_true()),
+            TokenKind::Equal => lhs.compare(rhs, i64::eq, u64::eq),
+            TokenKind::NotEqual => lhs.compare(rhs, i64::ne, u64::ne),
+            TokenKind::LessThan => lhs.compare(rhs, i64::lt, u64::lt),
+            TokenKind::GreaterThan => lhs.compare(rhs, i64::gt, u64::gt),
+            TokenKind::LessThanOrEqual => lhs.compare(rhs, i64::le, u64::le),
+            TokenKind::GreaterThanOrEqual => lhs.compare(rhs, i64::ge, u64::ge),
+            TokenKind::BinaryOperation(operation) => match operation {
+                BinaryOperation::Plus => lhs.arithmetic(rhs, i64::wrapping_add, u64::wrapping_add),
+                BinaryOperation::Minus => lhs.arithmetic(rhs, i64::wrapping_sub, u64::wrapping_sub),
+                BinaryOperation::Star => lhs.arithmetic(rhs, i64::wrapping_mul, u64::wrapping_mul),
+                BinaryOperation::Slash | BinaryOperation::Percent if !rhs.is_true() => {
+                    if !self.evaluated {
+                        return Some(Value::Signed(0));
+                    }
+
+                    self.preprocessor
+                        .error(ErrorCode::E0035, span, "the right operand is zero");
+                    return None;
+                }
+                BinaryOperation::Slash => lhs.arithmetic(rhs, i64::wrapping_div, u64::wrapping_div),
+                BinaryOperation::Percent => {
+                    lhs.arithmetic(rhs, i64::wrapping_rem, u64::wrapping_rem)
+                }
+                BinaryOperation::Caret => lhs.arithmetic(rhs, |a, b| a ^ b, |a, b| a ^ b),
+                BinaryOperation::And => lhs.arithmetic(rhs, |a, b| a & b, |a, b| a & b),
+                BinaryOperation::Or => lhs.arithmetic(rhs, |a, b| a | b, |a, b| a | b),
+                BinaryOperation::LeftShift => lhs.shift(rhs, true),
+                BinaryOperation::RightShift => lhs.shift(rhs, false),
+            },
+            _ => unreachable!("`{:?}` is not a binary operator", kind),
+        };
+
+        Some(value)
+    }
+
+    fn unary(&mut self) -> Option<Value> {
+        let Some(token) = self.tokens.get(self.index) else {
+            self.expected("expected an expression");
+            return None;
+        };
+        self.index += 1;
+
+        let value = match token.kind() {
+            TokenKind::BinaryOperation(BinaryOperation::Plus) => self.unary()?,
+            TokenKind::BinaryOperation(BinaryOperation::Minus) => match self.unary()? {
+                Value::Signed(value) => Value::Signed(value.wrapping_neg()),
+                Value::Unsigned(value) => Value::Unsigned(value.wrapping_neg()),
+            },
+            TokenKind::Tilde => match self.unary()? {
+                Value::Signed(value) => Value::Signed(!value),
+                Value::Unsigned(value) => Value::Unsigned(!value),
+            },
+            TokenKind::ExclamationMark => Value::from_bool(!self.unary()?.is_true()),
+            TokenKind::OpenDelimiter(Delimiter::Parenthesis) => {
+                let value = self.conditional()?;
+                if !self
+                    .tokens
+                    .get(self.index)
+                    .is_some_and(is_close_parenthesis)
+                {
+                    self.expected("expected `)`");
+                    return None;
+                }
+
+                self.index += 1;
+                value
+            }
+            // Identifiers left after macro expansion are 0, except for `true`
+            TokenKind::Identifier(keyword) => Value::from_bool(keyword == Keyword::True),
+            TokenKind::Literal(_) => self.literal(token)?,
+            _ => {
+                self.index -= 1;
+                self.expected("expected an expression");
+                return None;
+            }
+        };
+
+        Some(value)
+    }
+
+    fn literal(&self, token: &PpToken) -> Option<Value> {
+        let error = match (token.kind(), token.token.value()) {
+            (TokenKind::Literal(LiteralKind::Number), Some(LiteralValue::Integer(constant))) => {
+                let value = constant.value() as u64;
+                let is_signed = constant
+                    .integer_type(DataModel::default())
+                    .is_some_and(|integer_type| integer_type.is_signed());
+                if is_signed && i64::try_from(value).is_ok() {
+                    return Some(Value::Signed(value as i64));
+                }
+
+                return Some(Value::Unsigned(value));
+            }
+            (
+                TokenKind::Literal(LiteralKind::Character(encoding)),
+                Some(LiteralValue::Character(value)),
+            ) => {
+                return match encoding {
+                    Encoding::None | Encoding::Wide => Some(Value::Signed(i64::from(*value))),
+                    _ => Some(Value::Unsigned(u64::from(*value))),
+                };
+            }
+            (TokenKind::Literal(LiteralKind::Float), _) => {
+                String::from("floating constants are not allowed in conditions")
+            }
+            (TokenKind::Literal(LiteralKind::String(_)), _) => {
+                String::from("string literals are not allowed in conditions")
+            }
+            _ => format!(
+                "`{}` is not a valid constant",
+                self.preprocessor.spelling(&token.token)
+            ),
+        };
+
+        self.preprocessor
+            .error(ErrorCode::E0034, token.span(), error);
+        None
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        let is_kind = self
+            .tokens
+            .get(self.index)
+            .is_some_and(|token| token.kind() == kind);
+        self.index += usize::from(is_kind);
+        is_kind
+    }
+
+    /// Reports that the token at the current index, or the end of the
+    /// condition, is not what `message` expected.
+    fn expected(&self, message: &str) {
+        let span = self.tokens.get(self.index).map_or(self.end, PpToken::span);
+        self.preprocessor.error(ErrorCode::E0034, span, message);
+    }
+}
+
+/// Returns the precedence of a binary operator, which is higher the tighter
+/// it binds.
+fn binary_precedence(kind: TokenKind) -> Option<u8> {
+    let precedence = match kind {
+        TokenKind::LogicalOr => 1,
+        TokenKind::LogicalAnd => 2,
+        TokenKind::BinaryOperation(BinaryOperation::Or) => 3,
+        TokenKind::BinaryOperation(BinaryOperation::Caret) => 4,
+        TokenKind::BinaryOperation(BinaryOperation::And) => 5,
+        TokenKind::Equal | TokenKind::NotEqual => 6,
+        TokenKind::LessThan
+        | TokenKind::GreaterThan
+        | TokenKind::LessThanOrEqual
+        | TokenKind::GreaterThanOrEqual => 7,
+        TokenKind::BinaryOperation(BinaryOperation::LeftShift | BinaryOperation::RightShift) => 8,
+        TokenKind::BinaryOperation(BinaryOperation::Plus | BinaryOperation::Minus) => 9,
+        TokenKind::BinaryOperation(
+            BinaryOperation::Star | BinaryOperation::Slash | BinaryOperation::Percent,
+        ) => 10,
+        _ => return None,
+    };
+
+    Some(precedence)
+}
+
+/// Returns the token `1` if `value` is set and `0` otherwise.
+fn number(value: bool, span: Span) -> Token {
+    let constant = IntegerConstant::new(u128::from(value), Radix::Decimal, IntegerSuffix::None);
+    Token::new(TokenKind::Literal(LiteralKind::Number), span)
+        .with_symbol(Symbol::intern(if value { "1" } else { "0" }))
+        .with_value(LiteralValue::Integer(constant))
+}
@@ -0,0 +1,247 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{token::PpToken, Guard, Preprocessor};
+
+use hyperc_diagnostics::{annotation::Annotation, errors::ErrorCode, report::Report};
+use hyperc_lexer::token::{Encoding, LiteralKind, TokenKind};
+use hyperc_span::Span;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The system directories searched last, unless `-nostdinc` is given.
+pub const DEFAULT_SYSTEM_DIRECTORIES: &[&str] = &["/usr/local/include", "/usr/include"];
+
+/// The directories searched by `#include`, in the order of GCC and Clang.
+///
+/// `#include "..."` searches the directory of the including file and the
+/// directories of `-iquote` first. Both forms then search the directories of
+/// `-I`, of `-isystem` and finally the system directories.
+#[derive(Clone, Debug, Default)]
+pub struct IncludePaths {
+    quote_directories: Vec<PathBuf>,
+    directories: Vec<PathBuf>,
+    system_directories: Vec<PathBuf>,
+    default_directories: Vec<PathBuf>,
+}
+
+impl IncludePaths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_default_system_directories(mut self) -> Self {
+        self.default_directories = DEFAULT_SYSTEM_DIRECTORIES
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        self
+    }
+
+    /// Adds a directory given with `-iquote`.
+    pub fn add_quote_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.quote_directories.push(directory.into());
+    }
+
+    /// Adds a directory given with `-I`.
+    pub fn add_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.directories.push(directory.into());
+    }
+
+    /// Adds a directory given with `-isystem`.
+    pub fn add_system_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.system_directories.push(directory.into());
+    }
+
+    /// Returns the directories searched for a header in order, except for the
+    /// directory of the including file.
+    pub fn search_directories(&self, quoted: bool) -> impl Iterator<Item = &Path> {
+        let quote_directories = if quoted {
+            self.quote_directories.as_slice()
+        } else {
+            &[]
+        };
+
+        quote_directories
+            .iter()
+            .chain(&self.directories)
+            .chain(&self.system_directories)
+            .chain(&self.default_directories)
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns whether headers found in `directory` are system headers, which
+    /// are given with `-isystem` or are one of the system directories.
+    pub fn is_system_directory(&self, directory: &Path) -> bool {
+        self.system_directories
+            .iter()
+            .chain(&self.default_directories)
+            .any(|system_directory| system_directory == directory)
+    }
+}
+
+impl Preprocessor<'_> {
+    pub(crate) fn include(&mut self, line: &[PpToken]) {
+        let Some(first) = line.get(1) else {
+            self.error(
+                ErrorCode::E0029,
+                line[0].span().shrink_to_hi(),
+                "expected a file name",
+            );
+            return;
+        };
+
+        let span = first.span().to(line[line.len() - 1].span());
+
+        // Only a line that is not already a header name is macro-expanded
+        let mut header = line[1..].to_vec();
+        if first.kind() != TokenKind::HeaderName {
+            header = self.expand_tokens(&header);
+        }
+
+        let Some((name, quoted)) = self.header_name(&header) else {
+            self.error(ErrorCode::E0029, span, "expected a file name");
+            return;
+        };
+
+        let Some((path, system)) = self.find_header(&name, quoted) else {
+            self.error(
+                ErrorCode::E0028(name),
+                span,
+                "not found in the include paths",
+            );
+            return;
+        };
+
+        let identity = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if self.once.contains(&identity) {
+            return;
+        }
+
+        // A guarded header whose guard is still defined would expand to nothing
+        if self
+            .guards
+            .get(&identity)
+            .is_some_and(|guard| self.macros.contains_key(guard))
+        {
+            return;
+        }
+
+        if let Some(index) = self.files.iter().position(|file| file.identity == identity) {
+            // Including a header from inside its own guard is harmless
+            if let Guard::Open(guard) = self.files[index].guard {
+                if self.macros.contains_key(&guard) {
+                    return;
+                }
+            }
+
+            self.report_include_cycle(index, span, &name, &path);
+            return;
+        }
+
+        let source = match fs::read(&path) {
+            Ok(source) => source,
+            Err(error) => {
+                self.error(ErrorCode::E0028(name), span, error);
+                return;
+            }
+        };
+
+        let file_id = self
+            .diagnostic
+            .add_file(path.display(), String::from_utf8_lossy(&source));
+        // Only headers included by a user file with quotes are user files
+        let user = quoted && !system && self.files.last().is_some_and(|file| file.user);
+        self.enter_file(file_id, &source, path, Some(span), user);
+    }
+
+    /// Returns the file name of a header and whether it is quoted. A header
+    /// name produced by macro expansion is spelled from its tokens.
+    fn header_name(&self, tokens: &[PpToken]) -> Option<(String, bool)> {
+        match tokens {
+            [token] if token.kind() == TokenKind::HeaderName => {
+                let spelling = self.spelling(&token.token);
+                let quoted = spelling.starts_with('"');
+                Some((spelling[1..spelling.len() - 1].to_string(), quoted))
+            }
+            [token] if token.kind() == TokenKind::Literal(LiteralKind::String(Encoding::None)) => {
+                let spelling = self.spelling(&token.token);
+                Some((spelling[1..spelling.len() - 1].to_string(), true))
+            }
+            [open, name @ .., close]
+                if open.kind() == TokenKind::LessThan && close.kind() == TokenKind::GreaterThan =>
+            {
+                let mut spelling = String::new();
+                for (index, token) in name.iter().enumerate() {
+                    if index > 0 && token.leading_space {
+                        spelling.push(' ');
+                    }
+
+                    spelling.push_str(&self.spelling(&token.token));
+                }
+
+                Some((spelling, false))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the path of a header and whether it is in a system directory.
+    fn find_header(&self, name: &str, quoted: bool) -> Option<(PathBuf, bool)> {
+        let path = Path::new(name);
+        if path.is_absolute() {
+            return path.is_file().then(|| (path.to_path_buf(), false));
+        }
+
+        let current = self
+            .files
+            .last()
+            .filter(|_| quoted)
+            .map(|file| file.path.parent().unwrap_or(Path::new("")));
+
+        current
+            .into_iter()
+            .chain(self.include_paths.search_directories(quoted))
+            .map(|directory| (directory.join(name), directory))
+            .find(|(path, _)| path.is_file())
+            .map(|(path, directory)| (path, self.include_paths.is_system_directory(directory)))
+    }
+
+    /// Reports that including `path` from the innermost file reopens the file
+    /// at `index` of the include stack.
+    fn report_include_cycle(&self, index: usize, span: Span, name: &str, path: &Path) {
+        let mut annotations = vec![Annotation::primary(
+            span,
+            format!("`{}` is already being included", name),
+        )];
+        for file in &self.files[index + 1..] {
+            if let Some(include) = file.include {
+                annotations.push(Annotation::secondary(
+                    include,
+                    format!("includes `{}`", file.path.display()),
+                ));
+            }
+        }
+
+        let stack = self
+            .files
+            .iter()
+            .map(|file| file.path.as_path())
+            .chain([path])
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ");
+
+        let report = Report::error()
+            .with_error_code(ErrorCode::E0030(name.to_string()))
+            .with_annotations(annotations)
+            .with_note(format!("the include stack is {}", stack));
+        self.diagnostic.report(report);
+    }
+}
@@ -0,0 +1,678 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+mod conditional;
+mod expression;
+pub mod include;
+mod macros;
+mod tests;
+mod token;
+
+use crate::{conditional::Conditional, include::IncludePaths, macros::Macro, token::PpToken};
+
+use hyperc_diagnostics::{
+    annotation::Annotation,
+    errors::ErrorCode,
+    ice,
+    report::{Report, Severity},
+    sink::DiagnosticSink,
+    warnings::{Warning, WarningLevel, WarningSettings},
+    Diagnostic,
+};
+use hyperc_lexer::{
+    standard::Standard,
+    token::{Encoding, LiteralKind, LiteralValue, Token, TokenKind, Trivia},
+    Lexer,
+};
+use hyperc_span::{
+    source_map::{SourceFile, SourceMap},
+    symbol::Symbol,
+    FileId, Span,
+};
+
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::{BTreeSet, HashMap, HashSet},
+    fs, mem,
+    path::PathBuf,
+    rc::Rc,
+};
+
+/// The name of the file holding the spelling of pasted and stringified
+/// tokens.
+const SCRATCH_SPACE: &str = "<scratch space>";
+
+const VA_ARGS: &str = "__VA_ARGS__";
+const VA_OPT: &str = "__VA_OPT__";
+
+#[derive(Debug)]
+struct OpenFile {
+    file_id: FileId,
+    path: PathBuf,
+    /// The canonical path, which identifies the file across include paths.
+    identity: PathBuf,
+    /// The span of the `#include` directive that opened the file.
+    include: Option<Span>,
+    /// Whether the file was named on the command line or included from one,
+    /// rather than being a system header.
+    user: bool,
+    source: String,
+    /// The byte offset the next token is lexed from.
+    offset: usize,
+    /// Whether the next token starts a line, or has whitespace before it.
+    at_line_start: bool,
+    leading_space: bool,
+    conditionals: Vec<Conditional>,
+    guard: Guard,
+}
+
+/// How far a file is known to follow the include guard idiom, where the
+/// whole file is wrapped in `#ifndef X` ... `#endif`.
+#[derive(Clone, Copy, Debug)]
+enum Guard {
+    /// Nothing but whitespace and comments has been read yet.
+    Start,
+    /// The file started with `#ifndef X`, which has not ended yet.
+    Open(Symbol),
+    /// The `#ifndef X` has ended, and only whitespace and comments followed.
+    Closed(Symbol),
+    None,
+}
+
+/// Holds the reports of the lexer until they are attached to the token they
+/// belong to. Clones share the same storage.
+#[derive(Clone, Debug, Default)]
+struct DeferredSink {
+    reports: Rc<RefCell<Vec<Report>>>,
+}
+
+impl DeferredSink {
+    fn take(&self) -> Vec<Report> {
+        mem::take(&mut self.reports.borrow_mut())
+    }
+}
+
+impl DiagnosticSink for DeferredSink {
+    fn emit(&mut self, _source_map: &SourceMap, report: Report) {
+        self.reports.borrow_mut().push(report);
+    }
+}
+
+/// Executes the preprocessing directives of a file and expands its macros, as
+/// described by C11 6.10.
+///
+/// Files are lexed on demand, so that a directive such as a diagnostic pragma
+/// affects the lines after it.
+#[derive(Debug)]
+pub struct Preprocessor<'a> {
+    diagnostic: &'a Diagnostic,
+    standard: Standard,
+    macros: HashMap<Symbol, Rc<Macro>>,
+    include_paths: IncludePaths,
+    /// The files of the include stack, innermost last.
+    files: Vec<OpenFile>,
+    /// The files that contained `#pragma once`.
+    once: HashSet<PathBuf>,
+    /// The guard macros of the files that follow the include guard idiom.
+    guards: HashMap<PathBuf, Symbol>,
+    user_files: Vec<FileId>,
+    /// The file every pasted and stringified token is spelled in, one per
+    /// line.
+    scratch: OnceCell<FileId>,
+    /// Whether the innermost file is inside a skipped group.
+    skipping: bool,
+    /// The diagnostic the lexer reports to. It keeps every warning, so that
+    /// the settings in effect when a report is emitted decide about it.
+    deferred: Diagnostic,
+    deferred_sink: DeferredSink,
+    /// Tokens read before the rest of the open files, in reverse order. The
+    /// replacement of a macro is pushed here to be rescanned.
+    tokens: Vec<PpToken>,
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(diagnostic: &'a Diagnostic) -> Self {
+        let mut warning_settings = WarningSettings::new();
+        for warning in Warning::ALL {
+            warning_settings.set_level(*warning, WarningLevel::Warning);
+        }
+
+        let deferred_sink = DeferredSink::default();
+        Self {
+            diagnostic,
+            standard: Standard::default(),
+            macros: HashMap::new(),
+            include_paths: IncludePaths::new(),
+            files: Vec::new(),
+            once: HashSet::new(),
+            guards: HashMap::new(),
+            user_files: Vec::new(),
+            scratch: OnceCell::new(),
+            skipping: false,
+            deferred: Diagnostic::new()
+                .with_sink(deferred_sink.clone())
+                .with_warning_settings(warning_settings),
+            deferred_sink,
+            tokens: Vec::new(),
+        }
+    }
+
+    pub fn with_standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    pub fn with_include_paths(mut self, include_paths: IncludePaths) -> Self {
+        self.include_paths = include_paths;
+        self
+    }
+
+    /// The files that were named on the command line or included with quotes
+    /// outside of the system directories, which fix-its may be applied to.
+    pub fn user_files(&self) -> &[FileId] {
+        &self.user_files
+    }
+
+    /// Preprocesses the file `file_id`, whose text is `source`, into the tokens
+    /// read by the parser.
+    pub fn preprocess(&mut self, file_id: FileId, source: &[u8]) -> Vec<Token> {
+        let _stage = ice::enter_stage("preprocessing");
+
+        let path = PathBuf::from(
+            self.diagnostic
+                .source_map()
+                .get(file_id)
+                .map(SourceFile::name)
+                .unwrap_or_default(),
+        );
+        self.enter_file(file_id, source, path, None, true);
+
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token(true) {
+            self.report_token(&token);
+            tokens.push(token.token);
+        }
+
+        tokens
+    }
+
+    /// Emits the reports of the lexer about a token that is converted into a
+    /// token. Returns `false` if one of them is an error.
+    fn report_token(&self, token: &PpToken) -> bool {
+        let mut valid = true;
+        for report in &token.reports {
+            valid &= report.severity() != Severity::Error;
+            self.diagnostic.report(report.clone());
+        }
+
+        valid
+    }
+
+    fn enter_file(
+        &mut self,
+        file_id: FileId,
+        source: &[u8],
+        path: PathBuf,
+        include: Option<Span>,
+        user: bool,
+    ) {
+        // Reports the first invalid byte sequence
+        if Lexer::from_bytes(self.diagnostic, file_id, source).is_none() {
+            return;
+        }
+
+        if user {
+            self.user_files.push(file_id);
+        }
+
+        let identity = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        self.files.push(OpenFile {
+            file_id,
+            path,
+            identity,
+            include,
+            user,
+            source: String::from_utf8_lossy(source).into_owned(),
+            offset: 0,
+            at_line_start: true,
+            leading_space: false,
+            conditionals: Vec::new(),
+            guard: Guard::Start,
+        });
+    }
+
+    /// Closes the innermost file. Returns whether an including file is left.
+    fn leave_file(&mut self) -> bool {
+        let Some(file) = self.files.pop() else {
+            return false;
+        };
+
+        for conditional in file.conditionals {
+            self.error(
+                ErrorCode::E0032(conditional.directive),
+                conditional.span,
+                "the conditional is never ended",
+            );
+        }
+
+        if let Guard::Closed(guard) = file.guard {
+            self.guards.insert(file.identity, guard);
+        }
+
+        !self.files.is_empty()
+    }
+
+    /// Returns the next token that is neither part of a directive nor expanded
+    /// as a macro.
+    fn next_token(&mut self, directives: bool) -> Option<PpToken> {
+        while !self.diagnostic.has_reached_error_limit() {
+            let Some(token) = self.read_token() else {
+                if directives && self.leave_file() {
+                    continue;
+                }
+
+                return None;
+            };
+            ice::set_current_span(token.span());
+
+            if directives && token.at_line_start && token.kind() == TokenKind::PoundSign {
+                self.directive(token);
+                continue;
+            }
+
+            if directives {
+                self.end_guard();
+            }
+
+            if !self.expand(&token) {
+                return Some(token);
+            }
+        }
+
+        None
+    }
+
+    /// Reads the next token without expanding it.
+    fn read_token(&mut self) -> Option<PpToken> {
+        if let Some(token) = self.tokens.pop() {
+            return Some(token);
+        }
+
+        self.lex_token(false, false)
+    }
+
+    /// Lexes the next token of the innermost file. If `line` is set, no token
+    /// is lexed past the end of the current line, so that a directive affects
+    /// the lines after it. If `header_name` is set, a header name is lexed as
+    /// one token.
+    fn lex_token(&mut self, line: bool, header_name: bool) -> Option<PpToken> {
+        let file = self.files.last_mut()?;
+        if line && file.at_line_start {
+            return None;
+        }
+
+        let mut lexer = Lexer::new(&self.deferred, file.file_id, &file.source)
+            .with_standard(self.standard)
+            .with_trivia(true)
+            .with_header_name(header_name)
+            .with_pp_numbers(true)
+            .with_offset(file.offset);
+
+        let mut token = None;
+        for next in lexer.by_ref() {
+            let reports = self.deferred_sink.take();
+            if !matches!(next.kind(), TokenKind::Trivia(_)) {
+                token = Some((next, reports));
+                break;
+            }
+
+            // Comments are not converted into tokens, so their reports are
+            // emitted right away, except inside skipped groups
+            if !self.skipping {
+                for report in reports {
+                    self.diagnostic.report(report);
+                }
+            }
+
+            match next.kind() {
+                TokenKind::Trivia(Trivia::Newline) => {
+                    file.at_line_start = true;
+                    file.leading_space = true;
+                    if line {
+                        break;
+                    }
+                }
+                TokenKind::Trivia(Trivia::LineSplice) => {}
+                _ => file.leading_space = true,
+            }
+        }
+
+        file.offset = lexer.offset();
+        let token = token.map(|(token, reports)| PpToken {
+            token,
+            at_line_start: file.at_line_start,
+            leading_space: file.leading_space,
+            hide_set: BTreeSet::new(),
+            reports,
+        });
+        if token.is_some() {
+            file.at_line_start = false;
+            file.leading_space = false;
+        }
+
+        token
+    }
+
+    fn peek_token(&mut self) -> Option<&PpToken> {
+        if self.tokens.is_empty() {
+            let token = self.read_token()?;
+            self.tokens.push(token);
+        }
+
+        self.tokens.last()
+    }
+
+    /// Reads the rest of the line of a directive.
+    fn read_line(&mut self) -> Vec<PpToken> {
+        let mut line = Vec::<PpToken>::new();
+        loop {
+            // A header name can only follow `#include` (C11 6.4p4)
+            let header_name = matches!(line.as_slice(), [name] if name.is_identifier("include"));
+            let Some(token) = self.lex_token(true, header_name) else {
+                break;
+            };
+
+            line.push(token);
+        }
+
+        line
+    }
+
+    fn directive(&mut self, hash: PpToken) {
+        let line = self.read_line();
+        self.execute(&hash, &line);
+    }
+
+    fn execute(&mut self, hash: &PpToken, line: &[PpToken]) {
+        // Only an `#ifndef` at the start of the file can open its guard
+        let is_ifndef = line
+            .first()
+            .is_some_and(|name| name.is_identifier("ifndef"));
+        let at_start = self
+            .files
+            .last()
+            .is_some_and(|file| matches!(file.guard, Guard::Start));
+        if !is_ifndef || !at_start {
+            self.end_guard();
+        }
+
+        // A `#` on its own is the null directive, which does nothing
+        let Some(name) = line.first() else {
+            return;
+        };
+
+        match name.identifier().as_ref().map(Symbol::as_str) {
+            Some("define") => self.define(line),
+            Some("undef") => self.undef(line),
+            Some("include") => self.include(line),
+            Some("ifdef") => self.ifdef(hash, line, true),
+            Some("ifndef") => self.ifdef(hash, line, false),
+            Some("if") => self.if_(hash, line),
+            Some("elif" | "elifdef" | "elifndef" | "else") => self.else_(hash, line),
+            Some("endif") => self.endif(hash, line),
+            Some("line") => self.line(line),
+            Some("error" | "warning") => self.user_diagnostic(hash, line),
+            Some("pragma") => self.pragma(line),
+            Some("embed") => {
+                let directive = self.spelling(&name.token);
+                self.error(
+                    ErrorCode::E0039(directive),
+                    hash.span().to(name.span()),
+                    "not supported yet",
+                );
+            }
+            _ => {
+                let directive = self.spelling(&name.token);
+                self.error(
+                    ErrorCode::E0018(directive),
+                    hash.span().to(name.span()),
+                    "unknown directive",
+                );
+            }
+        }
+    }
+
+    /// Marks the innermost file as not guarded if it is still at its start or
+    /// after the `#endif` of its guard, since the guard would not cover the
+    /// whole file.
+    fn end_guard(&mut self) {
+        if let Some(file) = self.files.last_mut() {
+            if let Guard::Start | Guard::Closed(_) = file.guard {
+                file.guard = Guard::None;
+            }
+        }
+    }
+
+    /// Returns the macro name after the directive name at the start of `line`.
+    fn macro_name(&self, line: &[PpToken]) -> Option<Symbol> {
+        let (span, error) = match line.get(1) {
+            None => (
+                line[0].span().shrink_to_hi(),
+                String::from("expected a macro name"),
+            ),
+            Some(token) => match token.identifier() {
+                Some(name) if matches!(name.as_str(), "defined" | VA_ARGS | VA_OPT) => (
+                    token.span(),
+                    format!("`{}` cannot be used as a macro name", name),
+                ),
+                Some(name) => return Some(name),
+                None => (token.span(), String::from("expected an identifier")),
+            },
+        };
+
+        self.error(ErrorCode::E0019, span, error);
+        None
+    }
+
+    fn undef(&mut self, line: &[PpToken]) {
+        if let Some(name) = self.macro_name(line) {
+            self.macros.remove(&name);
+        }
+    }
+
+    /// Executes `#line`, which changes the line number and possibly the file
+    /// name shown for the following lines (C11 6.10.4).
+    fn line(&mut self, line: &[PpToken]) {
+        let tokens = self.expand_tokens(&line[1..]);
+        let (span, error) = match tokens.as_slice() {
+            [] => (line[0].span().shrink_to_hi(), "expected a line number"),
+            [number, rest @ ..] => {
+                let spelling = self.spelling(&number.token);
+                let value = spelling
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|_| spelling.bytes().all(|byte| byte.is_ascii_digit()))
+                    .filter(|value| (1..=2147483647).contains(value));
+
+                let name = match rest {
+                    [] => Ok(None),
+                    [name] => match (name.kind(), name.token.value()) {
+                        (
+                            TokenKind::Literal(LiteralKind::String(Encoding::None)),
+                            Some(LiteralValue::String(code_units)),
+                        ) => Ok(Some(
+                            code_units
+                                .iter()
+                                .filter_map(|code_unit| char::from_u32(*code_unit))
+                                .collect::<String>(),
+                        )),
+                        _ => Err((name.span(), "expected a file name as a string literal")),
+                    },
+                    [_, extra, ..] => Err((extra.span(), "expected the end of the directive")),
+                };
+
+                match (value, name) {
+                    (None, _) => (
+                        number.span(),
+                        "expected a decimal line number from 1 to 2147483647",
+                    ),
+                    (_, Err(error)) => error,
+                    (Some(value), Ok(name)) => {
+                        if let Some(file) = self.files.last() {
+                            let offset = line[line.len() - 1].span().end();
+                            self.diagnostic
+                                .add_line_directive(file.file_id, offset, value, name);
+                        }
+
+                        return;
+                    }
+                }
+            }
+        };
+
+        self.error(ErrorCode::E0038, span, error);
+    }
+
+    /// Executes `#error` or `#warning`, which report the rest of their line.
+    fn user_diagnostic(&self, hash: &PpToken, line: &[PpToken]) {
+        let mut message = String::new();
+        for token in &line[1..] {
+            if !message.is_empty() && token.leading_space {
+                message.push(' ');
+            }
+
+            message.push_str(&self.spelling(&token.token));
+        }
+
+        let directive = self.spelling(&line[0].token);
+        let report = if directive == "error" {
+            Report::error().with_error_code(ErrorCode::E0037(message))
+        } else {
+            Report::warning()
+                .with_message(format!("#warning {}", message).trim_end())
+                .with_warning(Warning::Cpp)
+        };
+
+        let span = hash.span().to(line[line.len() - 1].span());
+        let label = format!("reported by `#{}`", directive);
+        self.diagnostic
+            .report(report.with_annotations(vec![Annotation::primary(span, label)]));
+    }
+
+    fn pragma(&mut self, line: &[PpToken]) {
+        let words = line[1..]
+            .iter()
+            .map(|token| self.spelling(&token.token))
+            .collect::<Vec<_>>();
+
+        let warning = match words
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["hyperc", "diagnostic", arguments @ ..] => {
+                if self.diagnostic.apply_pragma(&arguments.join(" ")) {
+                    return;
+                }
+
+                "unknown diagnostic pragma ignored"
+            }
+            ["once"] => {
+                if let Some(file) = self.files.last() {
+                    self.once.insert(file.identity.clone());
+                }
+
+                return;
+            }
+            // The standard pragmas only affect floating-point code generation
+            ["STDC", ..] => return,
+            _ => "unknown pragma ignored",
+        };
+
+        let span = line[0].span().to(line[line.len() - 1].span());
+        let report = Report::warning()
+            .with_message(warning)
+            .with_warning(Warning::UnknownPragmas)
+            .with_annotations(vec![Annotation::primary(span, warning)]);
+        self.diagnostic.report(report);
+    }
+
+    /// The spelling of a token as written, with line splices removed.
+    fn spelling(&self, token: &Token) -> String {
+        if let Some(symbol) = token.symbol() {
+            return symbol.to_string();
+        }
+
+        let span = token.span();
+        let source_map = self.diagnostic.source_map();
+        let source = source_map
+            .get(span.file_id())
+            .map(SourceFile::source)
+            .unwrap_or_default();
+        let spelling = source.get(span.start()..span.end()).unwrap_or_default();
+        spelling.replace("\\\r\n", "").replace("\\\n", "")
+    }
+
+    /// Appends `text` as a new line of the scratch file and returns its span.
+    fn scratch_span(&self, text: &str) -> Span {
+        let file_id = *self
+            .scratch
+            .get_or_init(|| self.diagnostic.add_file(SCRATCH_SPACE, ""));
+        let start = self
+            .diagnostic
+            .append_to_file(file_id, &format!("{}\n", text))
+            .unwrap_or_default();
+
+        Span::new(file_id, start, start + text.len())
+    }
+
+    /// Spells `text` in the scratch file and lexes it as a token. Returns
+    /// `None` if `text` is not spelled like exactly one preprocessing token.
+    fn scratch_token(&self, text: &str) -> Option<PpToken> {
+        // Checks the text on its own first, so that invalid text is not spelled
+        let scratch = Diagnostic::new().with_sink(DeferredSink::default());
+        let file_id = scratch.add_file(SCRATCH_SPACE, text);
+        let mut lexer = Lexer::new(&scratch, file_id, text)
+            .with_standard(self.standard)
+            .with_pp_numbers(true);
+        let is_token = lexer.next().is_some_and(|token| {
+            token.kind() != TokenKind::Error && token.span().end() == text.len()
+        });
+        if !is_token {
+            return None;
+        }
+
+        Some(self.lex_scratch(text))
+    }
+
+    /// Spells `text` in the scratch file and lexes its first token.
+    fn lex_scratch(&self, text: &str) -> PpToken {
+        let span = self.scratch_span(text);
+        let source_map = self.diagnostic.source_map();
+        let source = source_map
+            .get(span.file_id())
+            .map(SourceFile::source)
+            .unwrap_or_default();
+        let token = Lexer::new(&self.deferred, span.file_id(), source)
+            .with_standard(self.standard)
+            .with_pp_numbers(true)
+            .with_offset(span.start())
+            .next()
+            .unwrap_or_else(|| Token::new(TokenKind::Error, span));
+
+        let mut token = PpToken::new(token);
+        token.reports = self.deferred_sink.take();
+        token
+    }
+
+    fn error(&self, error_code: ErrorCode, span: Span, message: impl ToString) {
+        let report = Report::error()
+            .with_error_code(error_code)
+            .with_annotations(vec![Annotation::primary(span, message)]);
+        self.diagnostic.report(report);
+    }
+}
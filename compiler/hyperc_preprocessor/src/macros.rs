@@ -0,0 +1,594 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{token::PpToken, Preprocessor, VA_ARGS, VA_OPT};
+
+use hyperc_diagnostics::{
+    annotation::Annotation, errors::ErrorCode, report::Report, warnings::Warning,
+};
+use hyperc_lexer::token::{Delimiter, LiteralKind, TokenKind};
+use hyperc_span::{symbol::Symbol, Span};
+
+use std::{mem, rc::Rc};
+
+#[derive(Debug)]
+pub(crate) struct Macro {
+    span: Span,
+    /// The parameters of a function-like macro. The variable arguments of a
+    /// variadic macro are its last parameter, `__VA_ARGS__`.
+    parameters: Option<Vec<Symbol>>,
+    variadic: bool,
+    replacement: Vec<PpToken>,
+}
+
+impl Macro {
+    fn is_function_like(&self) -> bool {
+        self.parameters.is_some()
+    }
+
+    fn parameter(&self, token: &PpToken) -> Option<usize> {
+        let name = token.identifier()?;
+        self.parameters
+            .as_ref()?
+            .iter()
+            .position(|parameter| *parameter == name)
+    }
+}
+
+/// An argument of a function-like macro invocation.
+struct Argument {
+    tokens: Vec<PpToken>,
+    /// The fully macro-expanded tokens, computed when first needed.
+    expanded: Option<Vec<PpToken>>,
+}
+
+impl Argument {
+    fn new(tokens: Vec<PpToken>) -> Self {
+        Self {
+            tokens,
+            expanded: None,
+        }
+    }
+}
+
+impl Preprocessor<'_> {
+    pub(crate) fn define(&mut self, line: &[PpToken]) {
+        let Some(name) = self.macro_name(line) else {
+            return;
+        };
+
+        let mut parameters = None;
+        let mut variadic = false;
+        let mut index = 2;
+
+        // Only a parenthesis directly after the name starts a parameter list
+        if line
+            .get(index)
+            .is_some_and(|token| is_open_parenthesis(token) && !token.leading_space)
+        {
+            let Some((list, is_variadic, end)) = self.parameters(line, index) else {
+                return;
+            };
+
+            parameters = Some(list);
+            variadic = is_variadic;
+            index = end;
+        }
+
+        let mut replacement = line[index..].to_vec();
+        if let Some(first) = replacement.first_mut() {
+            first.leading_space = false;
+        }
+
+        let definition = Macro {
+            span: line[1].span(),
+            parameters,
+            variadic,
+            replacement,
+        };
+        if !self.check_replacement(&definition) {
+            return;
+        }
+
+        if let Some(previous) = self.macros.get(&name) {
+            if !self.is_same_definition(previous, &definition) {
+                let warning = format!("`{}` redefined", name);
+                let report = Report::warning()
+                    .with_message(&warning)
+                    .with_warning(Warning::MacroRedefined)
+                    .with_annotations(vec![
+                        Annotation::primary(definition.span, warning),
+                        Annotation::secondary(previous.span, "previous definition is here"),
+                    ]);
+                self.diagnostic.report(report);
+            }
+        }
+
+        self.macros.insert(name, Rc::new(definition));
+    }
+
+    /// Parses the parameter list starting with the parenthesis at `open`.
+    /// Returns the parameters, whether the macro is variadic and the index
+    /// after the list.
+    fn parameters(&self, line: &[PpToken], open: usize) -> Option<(Vec<Symbol>, bool, usize)> {
+        let mut parameters = Vec::new();
+        let mut index = open + 1;
+
+        let (span, error) = loop {
+            let Some(token) = line.get(index) else {
+                let span = line[index - 1].span().shrink_to_hi();
+                break (span, String::from("expected `)`"));
+            };
+
+            if is_close_parenthesis(token) && parameters.is_empty() {
+                return Some((parameters, false, index + 1));
+            }
+
+            if token.kind() == TokenKind::Ellipsis {
+                if !line.get(index + 1).is_some_and(is_close_parenthesis) {
+                    let error = String::from("`...` has to be the last parameter");
+                    break (token.span(), error);
+                }
+
+                parameters.push(Symbol::intern(VA_ARGS));
+                return Some((parameters, true, index + 2));
+            }
+
+            let Some(parameter) = token.identifier() else {
+                break (token.span(), String::from("expected a parameter name"));
+            };
+
+            if matches!(parameter.as_str(), VA_ARGS | VA_OPT) {
+                let error = format!("`{}` cannot be used as a parameter name", parameter);
+                break (token.span(), error);
+            }
+
+            if parameters.contains(&parameter) {
+                break (token.span(), format!("duplicate parameter `{}`", parameter));
+            }
+
+            parameters.push(parameter);
+            index += 1;
+
+            match line.get(index) {
+                Some(token) if token.kind() == TokenKind::Comma => index += 1,
+                Some(token) if is_close_parenthesis(token) => {
+                    return Some((parameters, false, index + 1));
+                }
+                Some(token) => break (token.span(), String::from("expected `,` or `)`")),
+                None => {
+                    let span = line[index - 1].span().shrink_to_hi();
+                    break (span, String::from("expected `)`"));
+                }
+            }
+        };
+
+        self.error(ErrorCode::E0020, span, error);
+        None
+    }
+
+    /// Checks the constraints of C23 6.10.5 on the operators and special
+    /// identifiers of a replacement list.
+    fn check_replacement(&self, definition: &Macro) -> bool {
+        let replacement = &definition.replacement;
+
+        for token in [replacement.first(), replacement.last()]
+            .into_iter()
+            .flatten()
+        {
+            if is_paste(token) {
+                self.error(ErrorCode::E0022, token.span(), "`##` needs two operands");
+                return false;
+            }
+        }
+
+        let mut va_opt_end = None;
+        for (index, token) in replacement.iter().enumerate() {
+            if definition.is_function_like() && token.kind() == TokenKind::PoundSign {
+                let has_operand = replacement.get(index + 1).is_some_and(|operand| {
+                    definition.parameter(operand).is_some() || operand.is_identifier(VA_OPT)
+                });
+                if !has_operand {
+                    self.error(ErrorCode::E0021, token.span(), "expected a parameter");
+                    return false;
+                }
+            }
+
+            let identifier = token.identifier().filter(|identifier| {
+                matches!(identifier.as_str(), VA_ARGS | VA_OPT) && !definition.variadic
+            });
+            if let Some(identifier) = identifier {
+                let error = "the macro has no variable arguments";
+                self.error(
+                    ErrorCode::E0023(identifier.to_string()),
+                    token.span(),
+                    error,
+                );
+                return false;
+            }
+
+            if token.is_identifier(VA_OPT) {
+                let error = match find_va_opt_end(replacement, index) {
+                    _ if va_opt_end.is_some() => "`__VA_OPT__` cannot be nested",
+                    None if !replacement.get(index + 1).is_some_and(is_open_parenthesis) => {
+                        "expected `(` after `__VA_OPT__`"
+                    }
+                    None => "unclosed `__VA_OPT__`",
+                    Some(end) => {
+                        // The tokens of `__VA_OPT__` are a replacement list of their own
+                        let content = &replacement[index + 2..end];
+                        let paste = [content.first(), content.last()]
+                            .into_iter()
+                            .flatten()
+                            .find(|token| is_paste(token));
+                        if let Some(paste) = paste {
+                            self.error(ErrorCode::E0022, paste.span(), "`##` needs two operands");
+                            return false;
+                        }
+
+                        va_opt_end = Some(end);
+                        continue;
+                    }
+                };
+
+                self.error(ErrorCode::E0024, token.span(), error);
+                return false;
+            }
+
+            if va_opt_end == Some(index) {
+                va_opt_end = None;
+            }
+        }
+
+        true
+    }
+
+    /// Whether a redefinition is identical to the previous definition, which
+    /// C11 6.10.3p2 allows.
+    fn is_same_definition(&self, previous: &Macro, definition: &Macro) -> bool {
+        previous.parameters == definition.parameters
+            && previous.replacement.len() == definition.replacement.len()
+            && previous
+                .replacement
+                .iter()
+                .zip(&definition.replacement)
+                .all(|(previous, token)| {
+                    previous.leading_space == token.leading_space
+                        && self.spelling(&previous.token) == self.spelling(&token.token)
+                })
+    }
+
+    /// Expands `token` if it names a macro, and pushes the replacement back to
+    /// be rescanned. Returns `false` if the token is not expanded.
+    pub(crate) fn expand(&mut self, token: &PpToken) -> bool {
+        let Some(name) = token.identifier() else {
+            return false;
+        };
+
+        if token.hide_set.contains(&name) {
+            return false;
+        }
+
+        let Some(definition) = self.macros.get(&name).cloned() else {
+            return false;
+        };
+
+        // Following Prosser's algorithm, the replacement is hidden from the
+        // macro itself and from every macro that produced both its name and the
+        // closing parenthesis of its arguments
+        let (mut arguments, mut hide_set) = if definition.is_function_like() {
+            if !self.peek_token().is_some_and(is_open_parenthesis) {
+                return false;
+            }
+
+            let Some((arguments, close)) = self.arguments(token, &definition) else {
+                return true;
+            };
+
+            let hide_set = token.hide_set.intersection(&close.hide_set).copied();
+            (arguments, hide_set.collect())
+        } else {
+            (Vec::new(), token.hide_set.clone())
+        };
+        hide_set.insert(name);
+
+        let mut tokens = self
+            .substitute(&definition, &definition.replacement, &mut arguments)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        for replaced in &mut tokens {
+            replaced.at_line_start = false;
+            replaced.hide_set.extend(&hide_set);
+        }
+
+        if let Some(first) = tokens.first_mut() {
+            first.leading_space = token.leading_space;
+        }
+
+        self.tokens.extend(tokens.into_iter().rev());
+        true
+    }
+
+    /// Reads the arguments of an invocation of `definition`, up to and
+    /// including the closing parenthesis.
+    fn arguments(
+        &mut self,
+        name: &PpToken,
+        definition: &Macro,
+    ) -> Option<(Vec<Argument>, PpToken)> {
+        let parameter_count = definition.parameters.as_ref().map_or(0, Vec::len);
+        let open = self.read_token()?;
+
+        let mut arguments = Vec::new();
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        let close = loop {
+            let Some(token) = self.read_token() else {
+                let spelling = self.spelling(&name.token);
+                let error = "this parenthesis is never closed";
+                self.error(ErrorCode::E0025(spelling), open.span(), error);
+                return None;
+            };
+
+            match token.kind() {
+                TokenKind::OpenDelimiter(Delimiter::Parenthesis) => depth += 1,
+                TokenKind::CloseDelimiter(Delimiter::Parenthesis) if depth == 0 => break token,
+                TokenKind::CloseDelimiter(Delimiter::Parenthesis) => depth -= 1,
+                // Commas do not separate the variable arguments
+                TokenKind::Comma
+                    if depth == 0
+                        && !(definition.variadic && arguments.len() + 1 == parameter_count) =>
+                {
+                    arguments.push(Argument::new(mem::take(&mut tokens)));
+                    continue;
+                }
+                _ => {}
+            }
+
+            tokens.push(token);
+        };
+        arguments.push(Argument::new(tokens));
+
+        // An empty argument list passes nothing to a macro without parameters
+        if parameter_count == 0 && arguments[0].tokens.is_empty() {
+            arguments.clear();
+        }
+
+        // The variable arguments may be left out entirely since C23
+        if definition.variadic && arguments.len() + 1 == parameter_count {
+            arguments.push(Argument::new(Vec::new()));
+        }
+
+        if arguments.len() != parameter_count {
+            let count = parameter_count - usize::from(definition.variadic);
+            let error = format!(
+                "expected {}{} argument{}, found {}",
+                if definition.variadic { "at least " } else { "" },
+                count,
+                if count == 1 { "" } else { "s" },
+                arguments.len()
+            );
+            let spelling = self.spelling(&name.token);
+            self.error(ErrorCode::E0026(spelling), name.span(), error);
+            return None;
+        }
+
+        Some((arguments, close))
+    }
+
+    /// Replaces the parameters in `replacement` and applies the `#` and `##`
+    /// operators. The placemarkers that stand in for empty operands of `##`
+    /// are `None`.
+    fn substitute(
+        &mut self,
+        definition: &Macro,
+        replacement: &[PpToken],
+        arguments: &mut [Argument],
+    ) -> Vec<Option<PpToken>> {
+        let mut output = Vec::new();
+
+        let mut index = 0;
+        while index < replacement.len() {
+            let end = operand_end(definition, replacement, index);
+            let pasted = replacement.get(end).is_some_and(is_paste);
+            let operand = self.operand(definition, &replacement[index..end], arguments, pasted);
+            output.extend(operand);
+            index = end;
+
+            while let Some(operator) = replacement.get(index).filter(|token| is_paste(token)) {
+                let end = operand_end(definition, replacement, index + 1);
+                let operand = &replacement[index + 1..end];
+                let mut rhs = self
+                    .operand(definition, operand, arguments, true)
+                    .into_iter();
+
+                match (output.pop().flatten(), rhs.next().flatten()) {
+                    (Some(lhs), Some(rhs)) => {
+                        let pasted = self.paste(lhs, rhs, operator);
+                        output.extend(pasted.into_iter().map(Some));
+                    }
+                    (lhs, rhs) => output.push(lhs.or(rhs)),
+                }
+
+                output.extend(rhs);
+                index = end;
+            }
+        }
+
+        output
+    }
+
+    /// Replaces one operand of a replacement list. Operands of `#` and `##`
+    /// use their arguments as written, all others are fully macro-expanded
+    /// first.
+    fn operand(
+        &mut self,
+        definition: &Macro,
+        operand: &[PpToken],
+        arguments: &mut [Argument],
+        raw: bool,
+    ) -> Vec<Option<PpToken>> {
+        let token = &operand[0];
+
+        let mut tokens = if definition.is_function_like() && token.kind() == TokenKind::PoundSign {
+            let tokens = self
+                .operand(definition, &operand[1..], arguments, true)
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            vec![Some(self.stringify(&tokens))]
+        } else if let Some(parameter) = definition.parameter(token) {
+            let argument = &mut arguments[parameter];
+            let tokens = if raw {
+                &argument.tokens[..]
+            } else {
+                self.expand_argument(argument)
+            };
+
+            tokens.iter().cloned().map(Some).collect()
+        } else if token.is_identifier(VA_OPT) {
+            // Whether the variable arguments are empty is decided after their
+            // expansion, as clarified for C++20 by P1042R1
+            let arguments_empty = arguments
+                .last_mut()
+                .is_none_or(|argument| self.expand_argument(argument).is_empty());
+
+            if arguments_empty {
+                Vec::new()
+            } else {
+                let content = &operand[2..operand.len() - 1];
+                self.substitute(definition, content, arguments)
+            }
+        } else {
+            vec![Some(token.clone())]
+        };
+
+        if let Some(Some(first)) = tokens.first_mut() {
+            first.leading_space = token.leading_space;
+        }
+
+        if tokens.is_empty() {
+            tokens.push(None);
+        }
+
+        tokens
+    }
+
+    fn expand_argument<'b>(&mut self, argument: &'b mut Argument) -> &'b [PpToken] {
+        if argument.expanded.is_none() {
+            argument.expanded = Some(self.expand_tokens(&argument.tokens));
+        }
+
+        argument.expanded.as_deref().unwrap_or_default()
+    }
+
+    /// Fully macro-expands `tokens`, as if they formed the rest of the file.
+    pub(crate) fn expand_tokens(&mut self, tokens: &[PpToken]) -> Vec<PpToken> {
+        let tokens = tokens.iter().rev().cloned().collect();
+        let tokens = mem::replace(&mut self.tokens, tokens);
+        let files = mem::take(&mut self.files);
+
+        let mut expanded = Vec::new();
+        while let Some(token) = self.next_token(false) {
+            expanded.push(token);
+        }
+
+        self.tokens = tokens;
+        self.files = files;
+        expanded
+    }
+
+    /// Spells `tokens` as a string literal, as done by the `#` operator. The
+    /// literal is lexed like any other token, so that a backslash outside of
+    /// literals, as in `\n`, starts an escape sequence.
+    fn stringify(&self, tokens: &[PpToken]) -> PpToken {
+        let mut literal = String::from("\"");
+        for (index, token) in tokens.iter().enumerate() {
+            if index > 0 && token.leading_space {
+                literal.push(' ');
+            }
+
+            let text = self.spelling(&token.token);
+            match token.kind() {
+                TokenKind::Literal(LiteralKind::Character(_) | LiteralKind::String(_)) => {
+                    literal.push_str(&text.replace('\\', "\\\\").replace('"', "\\\""));
+                }
+                _ => literal.push_str(&text),
+            }
+        }
+        literal.push('"');
+
+        self.lex_scratch(&literal)
+    }
+
+    /// Pastes two tokens into one, as done by the `##` operator. Both are kept
+    /// if their spellings do not form a single token.
+    fn paste(&self, lhs: PpToken, rhs: PpToken, operator: &PpToken) -> Vec<PpToken> {
+        let lhs_spelling = self.spelling(&lhs.token);
+        let rhs_spelling = self.spelling(&rhs.token);
+        let spelling = format!("{}{}", lhs_spelling, rhs_spelling);
+
+        let Some(mut pasted) = self.scratch_token(&spelling) else {
+            let error = format!("`{}` is not a single token", spelling);
+            let error_code = ErrorCode::E0027(lhs_spelling, rhs_spelling);
+            self.error(error_code, operator.span(), error);
+            return vec![lhs, rhs];
+        };
+
+        pasted.leading_space = lhs.leading_space;
+        pasted.hide_set = lhs.hide_set.intersection(&rhs.hide_set).copied().collect();
+        vec![pasted]
+    }
+}
+
+/// Returns the index after the operand at `index`: a single token, `#` with
+/// its operand, or `__VA_OPT__` with its parenthesized tokens.
+fn operand_end(definition: &Macro, replacement: &[PpToken], index: usize) -> usize {
+    let token = &replacement[index];
+    if definition.is_function_like() && token.kind() == TokenKind::PoundSign {
+        return operand_end(definition, replacement, index + 1);
+    }
+
+    if definition.variadic && token.is_identifier(VA_OPT) {
+        return find_va_opt_end(replacement, index).map_or(index + 1, |end| end + 1);
+    }
+
+    index + 1
+}
+
+/// Returns the index of the parenthesis closing the `__VA_OPT__` at `index`.
+fn find_va_opt_end(replacement: &[PpToken], index: usize) -> Option<usize> {
+    if !replacement.get(index + 1).is_some_and(is_open_parenthesis) {
+        return None;
+    }
+
+    let mut depth = 0;
+    for (index, token) in replacement.iter().enumerate().skip(index + 1) {
+        if is_open_parenthesis(token) {
+            depth += 1;
+        } else if is_close_parenthesis(token) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+
+    None
+}
+
+pub(crate) fn is_open_parenthesis(token: &PpToken) -> bool {
+    token.kind() == TokenKind::OpenDelimiter(Delimiter::Parenthesis)
+}
+
+pub(crate) fn is_close_parenthesis(token: &PpToken) -> bool {
+    token.kind() == TokenKind::CloseDelimiter(Delimiter::Parenthesis)
+}
+
+fn is_paste(token: &PpToken) -> bool {
+    token.kind() == TokenKind::DoublePoundSign
+}
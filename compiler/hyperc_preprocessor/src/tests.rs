@@ -0,0 +1,1046 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![cfg(test)]
+
+use super::*;
+
+use hyperc_diagnostics::{
+    registry::EXPLANATIONS,
+    sink::{BufferSink, DisplayStyle, MemorySink},
+};
+use hyperc_lexer::token::{Encoding, LiteralValue};
+
+use std::{env, path::Path, process};
+
+/// Preprocesses `text` and spells the result with one space between tokens.
+fn preprocess(text: &str) -> (String, Vec<Report>) {
+    let (output, reports, _) = preprocess_file(
+        Path::new("test.c"),
+        &unindent::unindent(text),
+        IncludePaths::new(),
+    );
+    (output, reports)
+}
+
+/// Preprocesses the file at `path`, whose text is `text`, like [`preprocess`].
+/// Also returns the names of the files that were opened.
+fn preprocess_file(
+    path: &Path,
+    text: &str,
+    include_paths: IncludePaths,
+) -> (String, Vec<Report>, Vec<String>) {
+    let sink = MemorySink::new();
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let file_id = diagnostic.add_file(path.display(), text);
+
+    let mut preprocessor = Preprocessor::new(&diagnostic)
+        .with_standard(Standard::C23)
+        .with_include_paths(include_paths);
+    let tokens = preprocessor.preprocess(file_id, text.as_bytes());
+    let output = tokens
+        .iter()
+        .map(|token| preprocessor.spelling(token))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let files = diagnostic
+        .source_map()
+        .files()
+        .map(|(_, file)| file.name().to_string())
+        .filter(|name| name != SCRATCH_SPACE)
+        .collect();
+    (output, sink.reports(), files)
+}
+
+/// Writes `files` into a new temporary directory named after `name`.
+fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = env::temp_dir().join(format!("hyperc-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    for (path, text) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, unindent::unindent(text)).unwrap();
+    }
+
+    directory
+}
+
+/// Preprocesses `main.c` of `directory`.
+fn preprocess_main(
+    directory: &Path,
+    include_paths: IncludePaths,
+) -> (String, Vec<Report>, Vec<String>) {
+    let path = directory.join("main.c");
+    let text = fs::read_to_string(&path).unwrap();
+    preprocess_file(&path, &text, include_paths)
+}
+
+/// Lexes `text` and spells it the way [`preprocess`] does.
+fn spell(text: &str) -> String {
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file("expected.c", text);
+    let tokens = Lexer::new(&diagnostic, file_id, text)
+        .with_standard(Standard::C23)
+        .lex()
+        .unwrap();
+
+    let preprocessor = Preprocessor::new(&diagnostic);
+    tokens
+        .iter()
+        .map(|token| preprocessor.spelling(token))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn assert_expands(text: &str, expected: &str) {
+    let (output, reports) = preprocess(text);
+    assert_eq!(output, spell(expected));
+    assert!(reports.is_empty(), "unexpected reports: {:#?}", reports);
+}
+
+fn codes(reports: &[Report]) -> Vec<&str> {
+    reports.iter().filter_map(Report::code).collect()
+}
+
+#[test]
+fn object_like_macros() {
+    assert_expands(
+        r#"
+        #define EMPTY
+        #define ONE 1
+        #define TWO ONE + ONE
+        int a = TWO EMPTY;
+        #undef ONE
+        int b = TWO;
+        "#,
+        "int a = 1 + 1; int b = ONE + ONE;",
+    );
+}
+
+#[test]
+fn function_like_macros() {
+    assert_expands(
+        r#"
+        #define max(a, b) ((a) > (b) ? (a) : (b))
+        #define none() 0
+        #define id(x) x
+        int m = max(f(1, 2), (3, 4));
+        int n = none() + max
+        (
+            1,
+            2
+        );
+        int (max) = id() id(id)(5);
+        "#,
+        r#"
+        int m = ((f(1, 2)) > ((3, 4)) ? (f(1, 2)) : ((3, 4)));
+        int n = 0 + ((1) > (2) ? (1) : (2));
+        int (max) = id(5);
+        "#,
+    );
+}
+
+#[test]
+fn directives() {
+    assert_expands(
+        r#"
+        #
+          #  define A 1
+        # /* comment */ define B 2
+        int a = A # B;
+        #define C # define D 3
+        C
+        "#,
+        "int a = 1 # 2; # define D 3",
+    );
+}
+
+#[test]
+fn diagnostic_directives() {
+    let (output, reports) = preprocess(
+        r#"
+        #if 0
+        #error not reported
+        #warning not reported
+        #line 0
+        #embed "skipped.bin"
+        #endif
+        #error  version   2 is "required"
+        #warning don't
+        #error
+        #embed "data.bin"
+        a
+        "#,
+    );
+
+    assert_eq!(output, "a");
+    let messages = reports
+        .iter()
+        .map(|report| {
+            let code = report.code().map(ToString::to_string);
+            let message = report.message().map(ToString::to_string);
+            (report.severity(), code, message)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            (
+                Severity::Error,
+                Some(String::from("E0037")),
+                Some(String::from("#error version 2 is \"required\""))
+            ),
+            (
+                Severity::Warning,
+                None,
+                Some(String::from("#warning don't"))
+            ),
+            (
+                Severity::Error,
+                Some(String::from("E0037")),
+                Some(String::from("#error"))
+            ),
+            (
+                Severity::Error,
+                Some(String::from("E0039")),
+                Some(String::from("unsupported preprocessing directive `#embed`"))
+            ),
+        ]
+    );
+    assert_eq!(reports[1].named_warning(), Some(Warning::Cpp));
+}
+
+#[test]
+fn line_directives() {
+    let sink = BufferSink::new().with_display_style(DisplayStyle::Short);
+    let diagnostic = Diagnostic::new().with_sink(sink.clone());
+    let text = unindent::unindent(
+        r#"
+        #define LINE 100
+        #define FILE "parser.y"
+        #line LINE FILE
+        #warning first
+        #line 7
+        #warning second
+        "#,
+    );
+    let file_id = diagnostic.add_file("test.c", &text);
+    Preprocessor::new(&diagnostic).preprocess(file_id, text.as_bytes());
+
+    assert_eq!(
+        sink.contents(),
+        "parser.y:100:1: warning: #warning first [-Wcpp]\n\
+         parser.y:7:1: warning: #warning second [-Wcpp]\n"
+    );
+    assert_eq!(
+        diagnostic.source_map().get(file_id).unwrap().name(),
+        "test.c"
+    );
+
+    let (_, reports) = preprocess(
+        r#"
+        #line
+        #line 0
+        #line 0x10
+        #line 2147483648
+        #line 10 parser.y
+        #line 10 "parser.y" 1
+        #line 10 u8"parser.y"
+        "#,
+    );
+    assert_eq!(codes(&reports), ["E0038"; 7]);
+}
+
+#[test]
+fn rescanning() {
+    // C11 6.10.3.5 EXAMPLE 3
+    assert_expands(
+        r#"
+        #define x 3
+        #define f(a) f(x * (a))
+        #undef x
+        #define x 2
+        #define g f
+        #define z z[0]
+        #define h g(~
+        #define m(a) a(w)
+        #define w 0,1
+        #define t(a) a
+        #define p() int
+        #define q(x) x
+        #define r(x,y) x ## y
+        #define str(x) # x
+        f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);
+        g(x+(3,4)-w) | h 5) & m
+        (f)^m(m);
+        p() i[q()] = { q(1), r(2,3), r(4,), r(,5), r(,) };
+        char c[2][6] = { str(hello), str() };
+        "#,
+        r#"
+        f(2 * (y+1)) + f(2 * (f(2 * (z[0])))) % f(2 * (0)) + t(1);
+        f(2 * (2+(3,4)-0,1)) | f(2 * (~ 5)) & f(2 * (0,1))^m(0,1);
+        int i[] = { 1, 23, 4, 5, };
+        char c[2][6] = { "hello", "" };
+        "#,
+    );
+}
+
+#[test]
+fn blue_paint() {
+    assert_expands(
+        r#"
+        #define foo foo
+        #define a b
+        #define b a
+        #define id(x) x
+        #define f(x) x * g
+        #define g(x) f(x)
+        foo a b id(foo) id(a)
+        f(2)(9)
+        "#,
+        "foo a b foo a 2 * 9 * g",
+    );
+}
+
+#[test]
+fn stringification() {
+    // C11 6.10.3.5 EXAMPLE 4
+    assert_expands(
+        r#"
+        #define str(s) # s
+        #define xstr(s) str(s)
+        #define debug(s, t) printf("x" # s "= %d, x" # t "= %s", \
+                                   x ## s, x ## t)
+        #define glue(a, b) a ## b
+        #define xglue(a, b) glue(a, b)
+        #define HIGHLOW "hello"
+        #define LOW LOW ", world"
+        debug(1, 2);
+        fputs(str(strncmp("abc\0d", "abc", '\4') // this goes away
+                  == 0), s);
+        glue(HIGH, LOW);
+        xglue(HIGH, LOW)
+        xstr(  a   +
+               b  ) str(/* */x/**/y)
+        "#,
+        r#"
+        printf("x" "1" "= %d, x" "2" "= %s", x1, x2);
+        fputs("strncmp(\"abc\\0d\", \"abc\", '\\4') == 0", s);
+        "hello";
+        "hello" ", world"
+        "a + b" "x y"
+        "#,
+    );
+
+    let (_, reports) = preprocess("#define str(x) #x\nstr(\"a\\n\")");
+    assert!(reports.is_empty());
+
+    let diagnostic = Diagnostic::new();
+    let text = "#define str(x) #x\nstr(\"a\\n\" b)";
+    let file_id = diagnostic.add_file("test.c", text);
+    let tokens = Preprocessor::new(&diagnostic).preprocess(file_id, text.as_bytes());
+    let value = "\"a\\n\" b".bytes().map(u32::from).collect();
+    assert_eq!(tokens[0].value(), Some(&LiteralValue::String(value)));
+    assert_eq!(
+        tokens[0].kind(),
+        TokenKind::Literal(hyperc_lexer::token::LiteralKind::String(Encoding::None))
+    );
+}
+
+#[test]
+fn token_pasting() {
+    // C11 6.10.3.5 EXAMPLE 5
+    assert_expands(
+        r#"
+        #define t(x,y,z) x ## y ## z
+        int j[] = { t(1,2,3), t(,4,5), t(6,,7), t(8,9,),
+                    t(10,,), t(,11,), t(,,12), t(,,) };
+        #define cat(a, b) a ## b
+        #define hash_hash # ## #
+        #define mkstr(a) # a
+        #define in_between(a) mkstr(a)
+        #define join(c, d) in_between(c hash_hash d)
+        char p[] = join(x, y);
+        cat(<, <=) cat(L, 'a') cat(u8, "b")
+        "#,
+        r#"
+        int j[] = { 123, 45, 67, 89, 10, 11, 12, };
+        char p[] = "x ## y";
+        <<= L'a' u8"b"
+        "#,
+    );
+}
+
+#[test]
+fn pasting_numbers() {
+    // Every pasted operand is a preprocessing number, even if it is no
+    // constant, which is only reported once it is converted into a token
+    let (output, reports) = preprocess(
+        r#"
+        #define cat(a, b) a ## b
+        #define cat3(a, b, c) a ## b ## c
+        cat(1, e) cat(2, y) cat(0, xff) cat3(1, e, +) cat3(1, e, -) cat(., 5)
+        "#,
+    );
+
+    assert_eq!(output, "1e 2y 0xff 1e+ 1e- .5");
+    assert_eq!(codes(&reports), ["E0010", "E0036", "E0010", "E0010"]);
+
+    let (output, reports) = preprocess("#define cat(a, b) a ## b\ncat(1, +) cat(x, +)");
+    assert_eq!(output, "1 + x +");
+    assert_eq!(codes(&reports), ["E0027", "E0027"]);
+}
+
+#[test]
+fn preprocessing_tokens() {
+    // C11 6.10.3.5 EXAMPLE 4, where `@` and `\` are valid preprocessing tokens
+    let (output, reports) = preprocess("#define str(s) # s\nstr(: @\\n)");
+    assert_eq!(output, r#"": @\n""#);
+    assert!(reports.is_empty(), "unexpected reports: {:#?}", reports);
+
+    let diagnostic = Diagnostic::new();
+    let text = "#define str(s) # s\nstr(: @\\n)";
+    let file_id = diagnostic.add_file("test.c", text);
+    let tokens = Preprocessor::new(&diagnostic).preprocess(file_id, text.as_bytes());
+    let value = ": @\n".bytes().map(u32::from).collect();
+    assert_eq!(tokens[0].value(), Some(&LiteralValue::String(value)));
+
+    // Tokens are only diagnosed once they are converted
+    let (_, reports) = preprocess("#define x 1e\n#define y @");
+    assert!(reports.is_empty(), "unexpected reports: {:#?}", reports);
+
+    let (output, reports) = preprocess(
+        r#"
+        #define x 1e
+        #if 0
+        @ 1e 'a
+        #endif
+        x @ 10px
+        "#,
+    );
+    assert_eq!(output, "1e @ 10px");
+    assert_eq!(codes(&reports), ["E0010", "E0001", "E0036"]);
+
+    // A condition is converted after its expansion
+    let (output, reports) = preprocess("#define x 1e\n#if x\na\n#else\nb\n#endif");
+    assert_eq!(output, "b");
+    assert_eq!(codes(&reports), ["E0010"]);
+}
+
+#[test]
+fn scratch_space() {
+    let diagnostic = Diagnostic::new().with_sink(MemorySink::new());
+    let text = r#"
+        #define cat(a, b) a ## b
+        #define str(a) #a
+        cat(a, b) cat(1, 2) str(x) str(y z)
+    "#;
+    let file_id = diagnostic.add_file("test.c", text);
+    let tokens = Preprocessor::new(&diagnostic).preprocess(file_id, text.as_bytes());
+    assert_eq!(tokens.len(), 4);
+
+    // Every pasted and stringified token is spelled in the same file
+    let source_map = diagnostic.source_map();
+    let scratch = source_map
+        .files()
+        .filter(|(_, file)| file.name() == SCRATCH_SPACE)
+        .collect::<Vec<_>>();
+    assert_eq!(scratch.len(), 1);
+    assert_eq!(scratch[0].1.source(), "ab\n12\n\"x\"\n\"y z\"\n");
+    assert!(tokens
+        .iter()
+        .all(|token| token.span().file_id() == scratch[0].0));
+}
+
+#[test]
+fn variadic_macros() {
+    // C11 6.10.3.5 EXAMPLE 7
+    assert_expands(
+        r#"
+        #define debug(...) fprintf(stderr, __VA_ARGS__)
+        #define showlist(...) puts(#__VA_ARGS__)
+        #define report(test, ...) ((test)?puts(#test):\
+                    printf(__VA_ARGS__))
+        debug("Flag");
+        debug("X = %d\n", x);
+        showlist(The first, second, and third items.);
+        report(x>y, "x is %d but y is %d", x, y);
+        "#,
+        r#"
+        fprintf(stderr, "Flag");
+        fprintf(stderr, "X = %d\n", x);
+        puts("The first, second, and third items.");
+        ((x>y)?puts("x>y"): printf("x is %d but y is %d", x, y));
+        "#,
+    );
+}
+
+#[test]
+fn va_opt() {
+    // C23 6.10.5.1 EXAMPLE 6 and 7
+    assert_expands(
+        r#"
+        #define F(...) f(0 __VA_OPT__(,) __VA_ARGS__)
+        #define G(X, ...) f(0, X __VA_OPT__(,) __VA_ARGS__)
+        #define SDEF(sname, ...) S sname __VA_OPT__(= { __VA_ARGS__ })
+        #define EMP
+        F(a, b, c)
+        F()
+        F(EMP)
+        G(a, b, c)
+        G(a, )
+        G(a)
+        SDEF(foo);
+        SDEF(bar, 1, 2);
+        #define H2(X, Y, ...) __VA_OPT__(X ## Y,) __VA_ARGS__
+        H2(a, b, c, d)
+        #define H3(X, ...) #__VA_OPT__(X##X X##X)
+        H3(, 0)
+        #define H4(X, ...) __VA_OPT__(a X ## X) ## b
+        H4(, 1)
+        #define H5A(...) __VA_OPT__()/**/__VA_OPT__()
+        #define H5B(X) a ## X ## b
+        #define H5C(X) H5B(X)
+        H5C(H5A())
+        "#,
+        r#"
+        f(0, a, b, c)
+        f(0)
+        f(0)
+        f(0, a, b, c)
+        f(0, a)
+        f(0, a)
+        S foo;
+        S bar = { 1, 2 };
+        ab, c, d
+        ""
+        a b
+        ab
+        "#,
+    );
+}
+
+#[test]
+fn redefinitions() {
+    let (output, reports) = preprocess(
+        r#"
+        #define A(x) x  +  1
+        #define A(x) x + 1 /* same */
+        #define A(y) y + 1
+        #define B 1
+        #undef B
+        #define B 2
+        A(B)
+        "#,
+    );
+
+    assert_eq!(output, "2 + 1");
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].named_warning(), Some(Warning::MacroRedefined));
+    assert_eq!(reports[0].annotations().len(), 2);
+}
+
+#[test]
+fn pragmas() {
+    let (output, reports) = preprocess(
+        r#"
+        int a = 'ab';
+        #pragma hyperc diagnostic push
+        #pragma hyperc diagnostic ignored "-Wmultichar"
+        int b = 'cd';
+        #pragma hyperc diagnostic pop
+        int c = 'ef';
+        #pragma STDC FP_CONTRACT ON
+        #pragma hyperc diagnostic fatal "-Wmultichar"
+        #pragma unknown
+        "#,
+    );
+
+    assert_eq!(output, "int a = 'ab' ; int b = 'cd' ; int c = 'ef' ;");
+
+    let warnings = reports
+        .iter()
+        .map(|report| report.named_warning())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [Some(Warning::Multichar), Some(Warning::Multichar)]
+    );
+
+    let sink = MemorySink::new();
+    let mut settings = hyperc_diagnostics::warnings::WarningSettings::new();
+    settings.apply_option("unknown-pragmas");
+    let diagnostic = Diagnostic::new()
+        .with_sink(sink.clone())
+        .with_warning_settings(settings);
+    let text = "#pragma hyperc diagnostic fatal \"-Wmultichar\"\n#pragma unknown\n";
+    let file_id = diagnostic.add_file("test.c", text);
+    Preprocessor::new(&diagnostic).preprocess(file_id, text.as_bytes());
+
+    let messages = sink
+        .reports()
+        .iter()
+        .map(|report| report.message().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "unknown diagnostic pragma ignored",
+            "unknown pragma ignored"
+        ]
+    );
+}
+
+#[test]
+fn invalid_definitions() {
+    let (output, reports) = preprocess(
+        r#"
+        #define
+        #define 1
+        #undef defined
+        #define f(x
+        #define g(x, x) x
+        #define h(..., x)
+        #define i(x) #y
+        #define j(x) ## x
+        #define k __VA_ARGS__
+        #define l(...) __VA_OPT__
+        #define m(...) __VA_OPT__(__VA_OPT__())
+        #define n(...) __VA_OPT__(## x)
+        #define o(x) # x
+        #inclde <stdio.h>
+        o(1)
+        "#,
+    );
+
+    assert_eq!(output, "\"1\"");
+    assert_eq!(
+        codes(&reports),
+        [
+            "E0019", "E0019", "E0019", "E0020", "E0020", "E0020", "E0021", "E0022", "E0023",
+            "E0024", "E0024", "E0022", "E0018"
+        ]
+    );
+}
+
+#[test]
+fn invalid_invocations() {
+    let (output, reports) = preprocess(
+        r#"
+        #define f(x, y) x y
+        #define g(x, ...) x
+        #define h() 1
+        #define paste(a, b) a ## b
+        f(1) f(1, 2, 3) g() g(1) h(1) h()
+        paste(+, -) paste(., .)
+        f(1,
+        "#,
+    );
+
+    assert_eq!(output, "1 1 + - . .");
+    assert_eq!(
+        codes(&reports),
+        ["E0026", "E0026", "E0026", "E0027", "E0027", "E0025"]
+    );
+    assert!(reports
+        .iter()
+        .all(|report| report.severity() == Severity::Error));
+}
+
+#[test]
+fn include_search_order() {
+    let root = directory(
+        "search-order",
+        &[
+            (
+                "main/main.c",
+                r#"
+                #include "a.h"
+                #include <a.h>
+                #include "b.h"
+                #include <b.h>
+                #include <c.h>
+                #include "sub/d.h"
+                #define QUOTED "a.h"
+                #define ANGLED <a.h>
+                #include QUOTED
+                #include ANGLED
+                "#,
+            ),
+            ("main/a.h", "local"),
+            ("main/sub/d.h", "#include \"e.h\""),
+            ("main/sub/e.h", "sub_e"),
+            ("main/e.h", "main_e"),
+            ("quote/a.h", "quote_a"),
+            ("quote/b.h", "quote_b"),
+            ("quote/c.h", "quote_c"),
+            ("include/a.h", "include_a"),
+            ("include/b.h", "include_b"),
+            ("system/a.h", "system_a"),
+            ("system/c.h", "system_c"),
+        ],
+    );
+
+    let mut include_paths = IncludePaths::new();
+    include_paths.add_quote_directory(root.join("quote"));
+    include_paths.add_directory(root.join("include"));
+    include_paths.add_system_directory(root.join("system"));
+
+    let (output, reports, _) = preprocess_main(&root.join("main"), include_paths);
+    assert_eq!(
+        output,
+        "local include_a quote_b include_b system_c sub_e local include_a"
+    );
+    assert!(reports.is_empty(), "unexpected reports: {:#?}", reports);
+}
+
+#[test]
+fn user_files() {
+    let root = directory(
+        "user-files",
+        &[
+            (
+                "main.c",
+                r#"
+                #include "user.h"
+                #include <angled.h>
+                #include "system.h"
+                "#,
+            ),
+            ("user.h", ""),
+            ("include/angled.h", ""),
+            ("system/system.h", "#include \"nested.h\""),
+            ("system/nested.h", ""),
+        ],
+    );
+
+    let mut include_paths = IncludePaths::new();
+    include_paths.add_directory(root.join("include"));
+    include_paths.add_system_directory(root.join("system"));
+
+    let path = root.join("main.c");
+    let text = fs::read_to_string(&path).unwrap();
+    let diagnostic = Diagnostic::new();
+    let file_id = diagnostic.add_file(path.display(), &text);
+    let mut preprocessor = Preprocessor::new(&diagnostic).with_include_paths(include_paths);
+    preprocessor.preprocess(file_id, text.as_bytes());
+
+    let source_map = diagnostic.source_map();
+    let user_files = preprocessor
+        .user_files()
+        .iter()
+        .map(|&file_id| source_map.get(file_id).unwrap().name())
+        .map(|name| Path::new(name).file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(user_files, ["main.c", "user.h"]);
+    assert_eq!(source_map.files().count(), 5);
+}
+
+#[test]
+fn include_once() {
+    let root = directory(
+        "include-once",
+        &[
+            (
+                "main.c",
+                r#"
+                #include "once.h"
+                #include "once.h"
+                #include "guarded.h"
+                #include "guarded.h"
+                #include "unguarded.h"
+                #include "unguarded.h"
+                #undef GUARDED_H
+                #include "guarded.h"
+                #include "blocks.h"
+                #undef B
+                #include "blocks.h"
+                "#,
+            ),
+            ("once.h", "#pragma once\nonce"),
+            (
+                "guarded.h",
+                r#"
+                /* The guard may follow comments */
+                #ifndef GUARDED_H
+                #define GUARDED_H
+                guarded
+                #endif
+                "#,
+            ),
+            (
+                "unguarded.h",
+                r#"
+                #ifndef UNGUARDED_H
+                #define UNGUARDED_H
+                unguarded
+                #endif
+                trailer
+                "#,
+            ),
+            (
+                "blocks.h",
+                r#"
+                #ifndef A
+                #define A
+                a
+                #endif
+                #ifndef B
+                #define B
+                b
+                #endif
+                "#,
+            ),
+        ],
+    );
+
+    let (output, reports, files) = preprocess_main(&root, IncludePaths::new());
+    assert_eq!(
+        output,
+        "once guarded unguarded trailer trailer guarded a b b"
+    );
+    assert!(reports.is_empty(), "unexpected reports: {:#?}", reports);
+
+    let opened = |name: &str| files.iter().filter(|file| file.ends_with(name)).count();
+    assert_eq!(opened("once.h"), 1);
+    assert_eq!(opened("guarded.h") - opened("unguarded.h"), 2);
+    assert_eq!(opened("unguarded.h"), 2);
+}
+
+#[test]
+fn include_header_names() {
+    // Header names are taken as written, without escape sequences or comments
+    let root = directory(
+        "include-header-names",
+        &[
+            (
+                "main.c",
+                r#"
+                #include "dir\x.h"
+                #include <a//b.h> // comment
+                #include /* comment */ "a/b.h"
+                "#,
+            ),
+            ("dir\\x.h", "backslash"),
+            ("a/b.h", "slashes"),
+        ],
+    );
+
+    let mut include_paths = IncludePaths::new();
+    include_paths.add_directory(&root);
+    let (output, reports, _) = preprocess_main(&root, include_paths);
+    assert_eq!(output, "backslash slashes slashes");
+    assert!(reports.is_empty(), "unexpected reports: {:#?}", reports);
+}
+
+#[test]
+fn include_errors() {
+    let root = directory(
+        "include-errors",
+        &[
+            (
+                "main.c",
+                r#"
+                #include "missing.h"
+                #include <a.h>
+                #include
+                #include a.h
+                #include "a.h" extra
+                #include "a.h"
+                after
+                "#,
+            ),
+            ("a.h", "#include \"b.h\"\na"),
+            ("b.h", "#include \"a.h\"\nb"),
+        ],
+    );
+
+    let (output, reports, _) = preprocess_main(&root, IncludePaths::new());
+    assert_eq!(output, "b a after");
+    assert_eq!(
+        codes(&reports),
+        ["E0028", "E0028", "E0029", "E0029", "E0029", "E0030"]
+    );
+
+    let source_map_text = |report: &Report| {
+        let span = report.primary_span().unwrap();
+        let text = fs::read_to_string(root.join("main.c")).unwrap();
+        text[span.start()..span.end()].to_string()
+    };
+    assert_eq!(source_map_text(&reports[0]), "\"missing.h\"");
+    assert_eq!(source_map_text(&reports[1]), "<a.h>");
+
+    let cycle = &reports[5];
+    assert_eq!(cycle.annotations().len(), 2);
+    let stack = cycle.children()[0].message();
+    let files = stack
+        .trim_start_matches("the include stack is ")
+        .split(" -> ")
+        .map(|path| Path::new(path).file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(files, ["main.c", "a.h", "b.h", "a.h"]);
+}
+
+#[test]
+fn conditional_directives() {
+    assert_expands(
+        r#"
+        #define A
+        #ifdef A
+        a
+        #else
+        not_a
+        #endif
+        #ifndef A
+        not_a
+        #elifdef B
+        b
+        #elifndef C
+        not_c
+        #else
+        else
+        #endif
+        #ifdef B
+        #ifdef A
+        nested
+        #else
+        'unterminated
+        #endif
+        #elifdef A
+        elifdef_a
+        #elifdef A
+        twice
+        #endif
+        "#,
+        "a not_c elifdef_a",
+    );
+}
+
+#[test]
+fn invalid_conditionals() {
+    let (output, reports) = preprocess(
+        r#"
+        #endif
+        #else
+        #ifdef A
+        #else
+        #else
+        skipped
+        #endif
+        #if 1
+        if
+        #else
+        else
+        #endif
+        #ifndef B
+        b
+        "#,
+    );
+
+    assert_eq!(output, "if b");
+    assert_eq!(codes(&reports), ["E0031", "E0031", "E0033", "E0032"]);
+}
+
+#[test]
+fn conditions() {
+    assert_expands(
+        r#"
+        #define VERSION 3
+        #define PAIR(a, b) a + b
+        #if VERSION >= 2 && defined VERSION && defined(PAIR)
+        version
+        #endif
+        #if UNDEFINED || !defined(UNDEFINED) == 0
+        undefined
+        #elif PAIR(1, 2) * 2 == 5 && (1 ? 2 : 1 / 0) == 2
+        precedence
+        #endif
+        #if -1 < 0u
+        signed
+        #elif 0x10 >> 4 == 1 && 'a' == 97 && (1 << 3 | 1) == 011 && ~0 == -1
+        unsigned
+        #endif
+        #if 0 && 1 / 0 || 1 % 3 == 1 && 10 - 3 - 2 == 5
+        short_circuit
+        #endif
+        #if true && !false
+        keywords
+        #endif
+        "#,
+        "version precedence unsigned short_circuit keywords",
+    );
+}
+
+#[test]
+fn invalid_conditions() {
+    let (output, reports) = preprocess(
+        r#"
+        #if
+        empty
+        #elif 1 +
+        operand
+        #elif (1
+        parenthesis
+        #elif 1 2
+        operator
+        #elif defined(
+        defined
+        #elif 1.0 || "s"
+        float
+        #elif 1 / 0
+        division
+        #elif 1 ? 2
+        colon
+        #else
+        else
+        #endif
+        "#,
+    );
+
+    assert_eq!(output, "else");
+    assert_eq!(
+        codes(&reports),
+        ["E0034", "E0034", "E0034", "E0034", "E0034", "E0034", "E0035", "E0034"]
+    );
+}
+
+#[test]
+fn explanation_examples() {
+    // The examples include `config.h`, which is provided here
+    let headers = directory("explanations", &[("config.h", "")]);
+    let preprocess = |text: &str| {
+        let mut include_paths = IncludePaths::new();
+        include_paths.add_directory(&headers);
+        let (output, reports, _) = preprocess_file(
+            Path::new("test.c"),
+            &unindent::unindent(text),
+            include_paths,
+        );
+        (output, reports)
+    };
+
+    // The earlier error codes are reported by the lexer
+    for (code, explanation) in EXPLANATIONS.iter().filter(|(code, _)| *code >= "E0018") {
+        let examples = explanation
+            .split("```c\n")
+            .skip(1)
+            .map(|block| block.split("```").next().unwrap())
+            .collect::<Vec<_>>();
+        let (Some(erroneous), Some(fixed)) = (examples.first(), examples.last()) else {
+            continue;
+        };
+
+        let (_, reports) = preprocess(erroneous);
+        assert!(
+            codes(&reports).contains(code),
+            "the example of {code} should report {code}"
+        );
+
+        let (_, reports) = preprocess(fixed);
+        assert!(reports.is_empty(), "the fix of {code} should preprocess");
+    }
+}
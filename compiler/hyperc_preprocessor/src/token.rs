@@ -0,0 +1,64 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use hyperc_diagnostics::report::Report;
+use hyperc_lexer::token::{Token, TokenKind};
+use hyperc_span::{symbol::Symbol, Span};
+
+use std::collections::BTreeSet;
+
+/// A token together with the context the preprocessor needs about it.
+#[derive(Clone, Debug)]
+pub(crate) struct PpToken {
+    pub(crate) token: Token,
+    /// Whether the token is the first of its line, so that a `#` starts a
+    /// directive.
+    pub(crate) at_line_start: bool,
+    /// Whether whitespace separates the token from the one before it.
+    pub(crate) leading_space: bool,
+    /// The macros whose expansion produced the token. The token is never
+    /// expanded as one of these again, which the standard calls painting it
+    /// blue.
+    pub(crate) hide_set: BTreeSet<Symbol>,
+    /// The reports of the lexer about the token, which are only emitted once
+    /// it is converted into a token (C11 5.1.1.2p1.7), so that a stringified
+    /// or unused token is not diagnosed.
+    pub(crate) reports: Vec<Report>,
+}
+
+impl PpToken {
+    pub(crate) fn new(token: Token) -> Self {
+        Self {
+            token,
+            at_line_start: false,
+            leading_space: false,
+            hide_set: BTreeSet::new(),
+            reports: Vec::new(),
+        }
+    }
+
+    pub(crate) fn kind(&self) -> TokenKind {
+        self.token.kind()
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.token.span()
+    }
+
+    /// The spelling of an identifier. Keywords are identifiers to the
+    /// preprocessor too.
+    pub(crate) fn identifier(&self) -> Option<Symbol> {
+        match self.kind() {
+            TokenKind::Identifier(_) => self.token.symbol(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_identifier(&self, name: &str) -> bool {
+        self.identifier()
+            .is_some_and(|identifier| identifier.as_str() == name)
+    }
+}
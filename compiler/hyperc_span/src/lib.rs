@@ -4,17 +4,54 @@
  * SPDX-License-Identifier: MIT
  */
 
+pub mod source_map;
+pub mod symbol;
+mod tests;
+
 use std::fmt::{self, Display, Formatter};
 
-#[derive(Clone, Copy, Debug, Default)]
+/// Identifies a file registered in a [`SourceMap`](source_map::SourceMap).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(u32);
+
+impl FileId {
+    pub fn new(index: usize) -> Self {
+        Self(index as u32)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Display for FileId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A byte range inside one source file.
+///
+/// Spans order by file first and then by position, so sorting them sorts
+/// diagnostics in source order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Span {
+    file_id: FileId,
     start: usize,
     end: usize,
 }
 
 impl Span {
-    pub fn new(start: usize, end: usize) -> Self {
-        Self { start, end }
+    pub fn new(file_id: FileId, start: usize, end: usize) -> Self {
+        Self {
+            file_id,
+            start,
+            end,
+        }
+    }
+
+    pub fn file_id(&self) -> FileId {
+        self.file_id
     }
 
     pub fn start(&self) -> usize {
@@ -28,10 +65,62 @@ impl Span {
     pub fn length(&self) -> usize {
         self.end - self.start
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        debug_assert_eq!(self.file_id, other.file_id);
+
+        Span::new(
+            self.file_id,
+            self.start.min(other.start),
+            self.end.max(other.end),
+        )
+    }
+
+    /// Returns the span between `self` and `other`, which is empty if they
+    /// touch or overlap.
+    pub fn between(self, other: Span) -> Span {
+        debug_assert_eq!(self.file_id, other.file_id);
+
+        let (first, second) = if self <= other {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        Span::new(self.file_id, first.end, second.start.max(first.end))
+    }
+
+    pub fn contains(&self, other: Span) -> bool {
+        self.file_id == other.file_id && self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn contains_offset(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// Returns whether `self` and `other` share at least one byte.
+    pub fn intersects(&self, other: Span) -> bool {
+        self.file_id == other.file_id && self.start.max(other.start) < self.end.min(other.end)
+    }
+
+    /// Returns the empty span at the start of `self`.
+    pub fn shrink_to_lo(self) -> Span {
+        Span::new(self.file_id, self.start, self.start)
+    }
+
+    /// Returns the empty span at the end of `self`.
+    pub fn shrink_to_hi(self) -> Span {
+        Span::new(self.file_id, self.end, self.end)
+    }
 }
 
 impl Display for Span {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}..{}", self.start, self.end)
+        write!(f, "{}:{}..{}", self.file_id, self.start, self.end)
     }
 }
@@ -0,0 +1,238 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use crate::{FileId, Span};
+
+use std::ops::Range;
+
+/// A 1-based line and column position inside a [`SourceFile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    line: usize,
+    column: usize,
+    utf16_column: usize,
+}
+
+impl Location {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column counted in UTF-8 bytes.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The column counted in UTF-16 code units, as used by most editors.
+    pub fn utf16_column(&self) -> usize {
+        self.utf16_column
+    }
+}
+
+/// A `#line` directive, which changes the line numbers and possibly the name
+/// shown for the lines after it.
+#[derive(Clone, Debug)]
+struct LineDirective {
+    /// The 0-based index of the first line it applies to.
+    line_index: usize,
+    line: usize,
+    name: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    name: String,
+    source: String,
+    line_starts: Vec<usize>,
+    line_directives: Vec<LineDirective>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl ToString, source: impl ToString) -> Self {
+        let source = source.to_string();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            source,
+            line_starts,
+            line_directives: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Appends `text` to the end of the file and returns the offset it starts
+    /// at.
+    pub fn append(&mut self, text: &str) -> usize {
+        let offset = self.source.len();
+        self.source.push_str(text);
+        self.line_starts.extend(
+            text.match_indices('\n')
+                .map(|(index, _)| offset + index + 1),
+        );
+
+        offset
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Numbers the line after the one containing `offset` as `line`, and names
+    /// the file `name` from there on if it is set, as done by `#line` (C11
+    /// 6.10.4). The lines after it count up from `line`.
+    pub fn add_line_directive(&mut self, offset: usize, line: usize, name: Option<String>) {
+        let line_index = self.line_index(self.floor_char_boundary(offset)) + 1;
+        let name = name.or_else(|| {
+            self.line_directive(line_index)
+                .and_then(|directive| directive.name.clone())
+        });
+
+        self.line_directives.push(LineDirective {
+            line_index,
+            line,
+            name,
+        });
+    }
+
+    /// Returns the 1-based number of the line at the 0-based `line_index`, as
+    /// changed by `#line` directives.
+    pub fn presumed_line(&self, line_index: usize) -> usize {
+        match self.line_directive(line_index) {
+            Some(directive) => directive.line + (line_index - directive.line_index),
+            None => line_index + 1,
+        }
+    }
+
+    /// Returns the name of the file at `offset`, as changed by `#line`
+    /// directives.
+    pub fn presumed_name(&self, offset: usize) -> &str {
+        let line_index = self.line_index(self.floor_char_boundary(offset));
+        self.line_directive(line_index)
+            .and_then(|directive| directive.name.as_deref())
+            .unwrap_or(&self.name)
+    }
+
+    fn line_directive(&self, line_index: usize) -> Option<&LineDirective> {
+        self.line_directives
+            .iter()
+            .rev()
+            .find(|directive| directive.line_index <= line_index)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the 0-based index of the line containing `offset`.
+    pub fn line_index(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// Returns the byte range of the line at the 0-based `line_index`,
+    /// including its line terminator.
+    pub fn line_range(&self, line_index: usize) -> Range<usize> {
+        let start = self.line_starts[line_index];
+        let end = self
+            .line_starts
+            .get(line_index + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+
+        start..end
+    }
+
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = self.floor_char_boundary(offset);
+        let line_index = self.line_index(offset);
+        let line_start = self.line_starts[line_index];
+
+        let utf16_column = self.source[line_start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+
+        Location {
+            line: line_index + 1,
+            column: offset - line_start + 1,
+            utf16_column: utf16_column + 1,
+        }
+    }
+
+    pub fn span_location(&self, span: Span) -> (Location, Location) {
+        (self.location(span.start()), self.location(span.end()))
+    }
+
+    /// Returns the 1-based column of `offset` as displayed in a terminal, where
+    /// every character takes one cell and tabs advance to the next multiple of
+    /// `tab_width`.
+    pub fn display_column(&self, offset: usize, tab_width: usize) -> usize {
+        let offset = self.floor_char_boundary(offset);
+        let line_start = self.line_starts[self.line_index(offset)];
+
+        let column = self.source[line_start..offset]
+            .chars()
+            .fold(0, |column, character| match character {
+                '\t' if tab_width > 0 => (column / tab_width + 1) * tab_width,
+                _ => column + 1,
+            });
+
+        column + 1
+    }
+
+    fn floor_char_boundary(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        offset
+    }
+}
+
+/// Owns every source file of a compilation and hands out the [`FileId`]s
+/// that spans refer to.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl ToString, source: impl ToString) -> FileId {
+        let file_id = FileId::new(self.files.len());
+        self.files.push(SourceFile::new(name, source));
+        file_id
+    }
+
+    pub fn get(&self, file_id: FileId) -> Option<&SourceFile> {
+        self.files.get(file_id.index())
+    }
+
+    pub fn get_mut(&mut self, file_id: FileId) -> Option<&mut SourceFile> {
+        self.files.get_mut(file_id.index())
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| (FileId::new(index), file))
+    }
+
+    pub fn span_location(&self, span: Span) -> Option<(Location, Location)> {
+        self.get(span.file_id())
+            .map(|file| file.span_location(span))
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display, Formatter},
+    sync::{Mutex, OnceLock},
+};
+
+/// An interned string.
+///
+/// Symbols are cheap to copy and compare in O(1). The backing strings live
+/// for the rest of the program in a global interner.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn intern(string: &str) -> Self {
+        Interner::with(|interner| interner.intern(string))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        Interner::with(|interner| interner.get(*self))
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({:?})", self.as_str())
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn with<T>(f: impl FnOnce(&mut Interner) -> T) -> T {
+        static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+
+        let interner = INTERNER.get_or_init(Default::default);
+        let mut interner = interner.lock().unwrap_or_else(|error| error.into_inner());
+        f(&mut interner)
+    }
+
+    fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(string) {
+            return symbol;
+        }
+
+        let string: &'static str = Box::leak(string.into());
+        let symbol = Symbol(self.strings.len() as u32);
+        self.strings.push(string);
+        self.symbols.insert(string, symbol);
+
+        symbol
+    }
+
+    fn get(&self, symbol: Symbol) -> &'static str {
+        self.strings[symbol.0 as usize]
+    }
+}
@@ -0,0 +1,227 @@
+/*
+ * Copyright (c) 2023, SkillerRaptor
+ *
+ * SPDX-License-Identifier: MIT
+ */
+
+#![cfg(test)]
+
+use crate::{
+    source_map::{Location, SourceFile, SourceMap},
+    symbol::Symbol,
+    FileId, Span,
+};
+
+use proptest::prelude::*;
+
+#[test]
+fn symbol_intern() {
+    let first = Symbol::intern("main");
+    let second = Symbol::intern(&String::from("main"));
+    let third = Symbol::intern("mainly");
+
+    assert_eq!(first, second);
+    assert_ne!(first, third);
+    assert_eq!(first.as_str(), "main");
+    assert_eq!(third.to_string(), "mainly");
+}
+
+fn location(line: usize, column: usize, utf16_column: usize) -> (usize, usize, usize) {
+    (line, column, utf16_column)
+}
+
+fn unpack(location: Location) -> (usize, usize, usize) {
+    (location.line(), location.column(), location.utf16_column())
+}
+
+#[test]
+fn source_file_location() {
+    let file = SourceFile::new("test.c", "int a;\n\tchar *s = \"ö😀\";\n\nend");
+
+    assert_eq!(file.name(), "test.c");
+    assert_eq!(file.line_count(), 4);
+
+    assert_eq!(unpack(file.location(0)), location(1, 1, 1));
+    assert_eq!(unpack(file.location(4)), location(1, 5, 5));
+    assert_eq!(unpack(file.location(6)), location(1, 7, 7));
+    assert_eq!(unpack(file.location(7)), location(2, 1, 1));
+    assert_eq!(unpack(file.location(18)), location(2, 12, 12));
+    assert_eq!(unpack(file.location(20)), location(2, 13, 13));
+    assert_eq!(unpack(file.location(21)), location(2, 15, 14));
+    assert_eq!(unpack(file.location(25)), location(2, 19, 16));
+    assert_eq!(unpack(file.location(28)), location(3, 1, 1));
+    assert_eq!(unpack(file.location(32)), location(4, 4, 4));
+    assert_eq!(unpack(file.location(100)), location(4, 4, 4));
+
+    let (start, end) = file.span_location(Span::new(FileId::default(), 4, 18));
+    assert_eq!(unpack(start), location(1, 5, 5));
+    assert_eq!(unpack(end), location(2, 12, 12));
+
+    assert_eq!(file.line_range(1), 7..28);
+    assert_eq!(file.line_range(3), 29..32);
+}
+
+#[test]
+fn source_file_display_column() {
+    let file = SourceFile::new("test.c", "\ta\tb\n  \tc");
+
+    assert_eq!(file.display_column(0, 4), 1);
+    assert_eq!(file.display_column(1, 4), 5);
+    assert_eq!(file.display_column(3, 4), 9);
+    assert_eq!(file.display_column(3, 8), 17);
+    assert_eq!(file.display_column(8, 4), 5);
+    assert_eq!(file.display_column(8, 0), 4);
+}
+
+#[test]
+fn source_file_append() {
+    let mut file = SourceFile::new("<scratch space>", "");
+
+    assert_eq!(file.append("ab\n"), 0);
+    assert_eq!(file.append("c\nd\n"), 3);
+    assert_eq!(file.source(), "ab\nc\nd\n");
+    assert_eq!(file.line_count(), 4);
+    assert_eq!(unpack(file.location(5)), location(3, 1, 1));
+    assert_eq!(file.line_range(1), 3..5);
+}
+
+#[test]
+fn source_file_line_directives() {
+    let source = "a\n#line 10\nb\nc\n#line 20 \"gen.y\"\nd\n#line 5\ne\n";
+    let mut file = SourceFile::new("test.c", source);
+    file.add_line_directive(10, 10, None);
+    file.add_line_directive(30, 20, Some(String::from("gen.y")));
+    file.add_line_directive(40, 5, None);
+
+    let lines = (0..file.line_count())
+        .map(|line_index| file.presumed_line(line_index))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [1, 2, 10, 11, 12, 20, 21, 5, 6]);
+
+    // The name stays until another directive changes it
+    assert_eq!(file.presumed_name(0), "test.c");
+    assert_eq!(file.presumed_name(12), "test.c");
+    assert_eq!(file.presumed_name(32), "gen.y");
+    assert_eq!(file.presumed_name(41), "gen.y");
+    assert_eq!(file.name(), "test.c");
+}
+
+#[test]
+fn source_map_files() {
+    let mut source_map = SourceMap::new();
+    let source_id = source_map.add("test.c", "#include \"test.h\"\nint a;\n");
+    let header_id = source_map.add("test.h", "int b;\n");
+
+    assert_ne!(source_id, header_id);
+    assert_eq!(source_map.get(source_id).unwrap().name(), "test.c");
+    assert_eq!(source_map.get(header_id).unwrap().name(), "test.h");
+    assert!(source_map.get(FileId::new(2)).is_none());
+    assert_eq!(source_map.files().count(), 2);
+
+    let (start, end) = source_map
+        .span_location(Span::new(source_id, 22, 23))
+        .unwrap();
+    assert_eq!((start.line(), start.column()), (2, 5));
+    assert_eq!((end.line(), end.column()), (2, 6));
+
+    let (start, _) = source_map
+        .span_location(Span::new(header_id, 4, 5))
+        .unwrap();
+    assert_eq!((start.line(), start.column()), (1, 5));
+}
+
+#[test]
+fn span_combinators() {
+    let file_id = FileId::default();
+    let a = Span::new(file_id, 2, 5);
+    let b = Span::new(file_id, 8, 12);
+
+    assert_eq!(a.to(b), Span::new(file_id, 2, 12));
+    assert_eq!(b.to(a), Span::new(file_id, 2, 12));
+    assert_eq!(a.between(b), Span::new(file_id, 5, 8));
+    assert_eq!(b.between(a), Span::new(file_id, 5, 8));
+    assert_eq!(a.shrink_to_lo(), Span::new(file_id, 2, 2));
+    assert_eq!(a.shrink_to_hi(), Span::new(file_id, 5, 5));
+
+    assert!(a.to(b).contains(a));
+    assert!(!a.contains(b));
+    assert!(a.contains_offset(2));
+    assert!(!a.contains_offset(5));
+
+    assert!(a.intersects(Span::new(file_id, 4, 9)));
+    assert!(!a.intersects(Span::new(file_id, 5, 9)));
+    assert!(!a.intersects(Span::new(FileId::new(1), 2, 5)));
+    assert!(!a.contains(Span::new(FileId::new(1), 2, 5)));
+}
+
+#[test]
+fn span_ordering() {
+    let mut spans = vec![
+        Span::new(FileId::new(1), 0, 1),
+        Span::new(FileId::new(0), 4, 6),
+        Span::new(FileId::new(0), 4, 5),
+        Span::new(FileId::new(0), 1, 9),
+    ];
+    spans.sort();
+
+    assert_eq!(
+        spans,
+        vec![
+            Span::new(FileId::new(0), 1, 9),
+            Span::new(FileId::new(0), 4, 5),
+            Span::new(FileId::new(0), 4, 6),
+            Span::new(FileId::new(1), 0, 1),
+        ]
+    );
+}
+
+fn span() -> impl Strategy<Value = Span> {
+    (0usize..1000, 0usize..1000).prop_map(|(a, b)| Span::new(FileId::default(), a.min(b), a.max(b)))
+}
+
+proptest! {
+    #[test]
+    fn span_to_covers_both(a in span(), b in span()) {
+        let merged = a.to(b);
+
+        prop_assert!(merged.contains(a));
+        prop_assert!(merged.contains(b));
+        prop_assert_eq!(merged, b.to(a));
+        prop_assert_eq!(merged.to(a), merged);
+    }
+
+    #[test]
+    fn span_intersects_is_symmetric(a in span(), b in span()) {
+        prop_assert_eq!(a.intersects(b), b.intersects(a));
+        prop_assert_eq!(a.intersects(a), !a.is_empty());
+
+        if a.intersects(b) {
+            prop_assert!(a.to(b).length() < a.length() + b.length());
+        }
+    }
+
+    #[test]
+    fn span_between_fills_gap(a in span(), b in span()) {
+        let between = a.between(b);
+
+        prop_assert_eq!(between, b.between(a));
+        prop_assert!(!between.intersects(a));
+        prop_assert!(!between.intersects(b));
+        prop_assert!(a.to(b).contains(between));
+
+        if !a.intersects(b) && !a.contains(b) && !b.contains(a) {
+            prop_assert_eq!(
+                a.length() + between.length() + b.length(),
+                a.to(b).length()
+            );
+        }
+    }
+
+    #[test]
+    fn span_shrink_is_contained(a in span()) {
+        prop_assert!(a.contains(a.shrink_to_lo()));
+        prop_assert!(a.contains(a.shrink_to_hi()));
+        prop_assert!(a.shrink_to_lo().is_empty());
+        prop_assert_eq!(a.shrink_to_lo().to(a.shrink_to_hi()), a);
+    }
+}